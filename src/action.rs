@@ -0,0 +1,80 @@
+/// built-in actions that operate on the window manager itself, those can be
+/// bound to combos instead of spawning a process
+/// ```toml
+/// [combo.workspace-1]
+/// keys = ["1"]
+/// action = { view-workspace = 1 }
+///
+/// [combo.previous-workspace]
+/// keys = ["Tab"]
+/// action = "workspace-back-and-forth"
+///
+/// [combo.monocle]
+/// keys = ["m"]
+/// action = { set-layout = "monocle" }
+/// ```
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::layout::LayoutKind;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    /// shows the given workspace, workspaces are numbered from 1
+    ViewWorkspace(usize),
+
+    /// moves the focused window to the given workspace, without
+    /// leaving the current workspace
+    MoveToWorkspace(usize),
+
+    /// shows the workspace that was shown before the current one
+    WorkspaceBackAndForth,
+
+    /// closes the focused window
+    Close,
+
+    /// focuses the window after the focused window in the workspace
+    FocusNext,
+
+    /// focuses the window before the focused window in the workspace
+    FocusPrevious,
+
+    /// swaps the focused window with the first master window
+    SwapMaster,
+
+    /// makes the focused window fullscreen, or gives it back its place
+    ToggleFullscreen,
+
+    /// switches the current workspace to the next available layout
+    NextLayout,
+
+    /// switches the current workspace to the given layout
+    SetLayout(LayoutKind),
+
+    /// reads the config file again and applies it
+    Reload,
+
+    /// stops the window manager
+    Quit,
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::ViewWorkspace(n) => write!(fmt, "view-workspace {}", n),
+            Action::MoveToWorkspace(n) => write!(fmt, "move-to-workspace {}", n),
+            Action::WorkspaceBackAndForth => write!(fmt, "workspace-back-and-forth"),
+            Action::Close => write!(fmt, "close"),
+            Action::FocusNext => write!(fmt, "focus-next"),
+            Action::FocusPrevious => write!(fmt, "focus-previous"),
+            Action::SwapMaster => write!(fmt, "swap-master"),
+            Action::ToggleFullscreen => write!(fmt, "toggle-fullscreen"),
+            Action::NextLayout => write!(fmt, "next-layout"),
+            Action::SetLayout(kind) => write!(fmt, "set-layout {}", kind),
+            Action::Reload => write!(fmt, "reload"),
+            Action::Quit => write!(fmt, "quit"),
+        }
+    }
+}
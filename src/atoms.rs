@@ -0,0 +1,66 @@
+use x11rb_async::connection::Connection;
+use x11rb_async::protocol::xproto::{Atom, ConnectionExt as _};
+
+/// defines the `Atoms` struct with a field for each given atom name, the
+/// atoms are interned once when the window manager starts
+macro_rules! atoms {
+    ($($name:ident),* $(,)?) => {
+        #[allow(non_snake_case)]
+        #[derive(Debug, Clone, Copy)]
+        pub struct Atoms {
+            $(pub $name: Atom,)*
+        }
+
+        impl Atoms {
+            /// interns all the atoms, all the requests are sent
+            /// before waiting for the first reply
+            #[allow(non_snake_case)]
+            pub async fn intern<C>(connection: &C) -> anyhow::Result<Self>
+            where
+                C: Connection,
+            {
+                $(
+                    let $name = connection
+                        .intern_atom(false, stringify!($name).as_bytes())
+                        .await?;
+                )*
+
+                Ok(Self {
+                    $($name: $name.reply().await?.atom,)*
+                })
+            }
+        }
+    };
+}
+
+atoms! {
+    WM_PROTOCOLS,
+    WM_DELETE_WINDOW,
+    WM_TAKE_FOCUS,
+    WM_STATE,
+    MANAGER,
+    UTF8_STRING,
+    _NET_WM_NAME,
+    _NET_SUPPORTED,
+    _NET_SUPPORTING_WM_CHECK,
+    _NET_CLIENT_LIST,
+    _NET_CLIENT_LIST_STACKING,
+    _NET_NUMBER_OF_DESKTOPS,
+    _NET_DESKTOP_NAMES,
+    _NET_CURRENT_DESKTOP,
+    _NET_ACTIVE_WINDOW,
+    _NET_WM_DESKTOP,
+    _NET_WM_STATE,
+    _NET_WM_STATE_FULLSCREEN,
+    _NET_WM_STATE_DEMANDS_ATTENTION,
+    _NET_WM_WINDOW_TYPE,
+    _NET_WM_STRUT,
+    _NET_WM_STRUT_PARTIAL,
+    _NET_WM_WINDOW_TYPE_NORMAL,
+    _NET_WM_WINDOW_TYPE_DOCK,
+    _NET_WM_WINDOW_TYPE_DIALOG,
+    _NET_WM_WINDOW_TYPE_UTILITY,
+    _NET_WM_WINDOW_TYPE_SPLASH,
+    _NET_WM_WINDOW_TYPE_NOTIFICATION,
+    _NET_WM_WINDOW_TYPE_TOOLTIP,
+}
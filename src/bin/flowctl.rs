@@ -0,0 +1,243 @@
+/// command line client for a running flow instance, it sends a single
+/// request over the ipc socket and prints the reply
+///
+/// ```text
+/// flowctl workspace 3
+/// flowctl close
+/// flowctl --json tree
+/// flowctl subscribe
+/// ```
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand, ValueEnum};
+
+use flow::ipc::{Event, Reply, Request, Tree};
+use flow::{Action, LayoutKind, SpawnCommand};
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "flowctl",
+    version,
+    about = "control a running flow window manager"
+)]
+struct Cli {
+    /// print the raw JSON replies instead of human readable output
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// the ipc socket, defaults to the socket of the `DISPLAY` instance
+    #[arg(long, global = true, value_name = "PATH")]
+    socket: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// show the given workspace, numbered from 1
+    Workspace { number: usize },
+
+    /// move the focused window to the given workspace
+    Move { number: usize },
+
+    /// show the previously shown workspace
+    BackAndForth,
+
+    /// close the focused window
+    Close,
+
+    /// move the focus to the next or previous window
+    Focus {
+        #[arg(value_enum)]
+        direction: Direction,
+    },
+
+    /// swap the focused window with the master window
+    SwapMaster,
+
+    /// toggle fullscreen for the focused window
+    Fullscreen,
+
+    /// set the layout of the current workspace, or switch
+    /// to the next layout if none is given
+    Layout {
+        #[arg(value_parser = parse_layout)]
+        layout: Option<LayoutKind>,
+    },
+
+    /// reload the config file
+    Reload,
+
+    /// stop the window manager
+    Quit,
+
+    /// spawn a process from the window manager
+    Spawn {
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+    },
+
+    /// print the workspaces and their windows
+    Tree,
+
+    /// print the window manager events as they happen
+    Subscribe,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Direction {
+    Next,
+    Previous,
+}
+
+fn parse_layout(value: &str) -> Result<LayoutKind, String> {
+    serde_json::from_value(serde_json::Value::String(value.to_string()))
+        .map_err(|_| format!("unknown layout `{}`", value))
+}
+
+impl Command {
+    fn request(self) -> Request {
+        let action = match self {
+            Command::Workspace { number } => Action::ViewWorkspace(number),
+            Command::Move { number } => Action::MoveToWorkspace(number),
+            Command::BackAndForth => Action::WorkspaceBackAndForth,
+            Command::Close => Action::Close,
+            Command::Focus {
+                direction: Direction::Next,
+            } => Action::FocusNext,
+            Command::Focus {
+                direction: Direction::Previous,
+            } => Action::FocusPrevious,
+            Command::SwapMaster => Action::SwapMaster,
+            Command::Fullscreen => Action::ToggleFullscreen,
+            Command::Layout { layout: None } => Action::NextLayout,
+            Command::Layout {
+                layout: Some(layout),
+            } => Action::SetLayout(layout),
+            Command::Reload => Action::Reload,
+            Command::Quit => Action::Quit,
+            Command::Spawn { command } => return Request::Spawn(SpawnCommand::Exec(command)),
+            Command::Tree => return Request::Tree,
+            Command::Subscribe => return Request::Subscribe,
+        };
+        Request::Action(action)
+    }
+}
+
+fn run(cli: Cli) -> anyhow::Result<()> {
+    let path = match cli.socket {
+        Some(path) => path,
+        None => flow::ipc::default_socket_path()?,
+    };
+    let mut stream = UnixStream::connect(&path)
+        .map_err(|err| anyhow::anyhow!("couldn't connect to `{}`, {}", path.display(), err))?;
+
+    let request = cli.command.request();
+    let mut line = serde_json::to_string(&request)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+
+    let mut lines = BufReader::new(stream).lines();
+    let line = lines
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("the window manager closed the connection"))??;
+    let reply: Reply = serde_json::from_str(&line)?;
+    if !reply.success {
+        anyhow::bail!(reply.error.unwrap_or_else(|| "request failed".to_string()));
+    }
+
+    match request {
+        Request::Subscribe => {
+            for line in lines {
+                let line = line?;
+                if cli.json {
+                    println!("{}", line);
+                } else {
+                    print_event(&serde_json::from_str(&line)?);
+                }
+            }
+        }
+        Request::Tree if cli.json => println!("{}", line),
+        Request::Tree => {
+            let data = reply
+                .data
+                .ok_or_else(|| anyhow::anyhow!("the tree reply has no data"))?;
+            print_tree(&serde_json::from_value(data)?);
+        }
+        _ if cli.json => println!("{}", line),
+        _ => {}
+    }
+    Ok(())
+}
+
+fn print_tree(tree: &Tree) {
+    for workspace in &tree.workspaces {
+        let current = if workspace.current { " *" } else { "" };
+        println!(
+            "workspace {} [{}]{}",
+            workspace.number, workspace.layout, current
+        );
+
+        for window in &workspace.windows {
+            let focused = if workspace.focused == Some(window.id) {
+                "> "
+            } else {
+                "  "
+            };
+            let geometry = window.geometry;
+            println!(
+                "  {}{:#x} {}x{}+{}+{} {}",
+                focused,
+                window.id,
+                geometry.width,
+                geometry.height,
+                geometry.x,
+                geometry.y,
+                window.title
+            );
+        }
+    }
+}
+
+fn print_event(event: &Event) {
+    match event {
+        Event::WorkspaceChanged { workspace } => println!("workspace changed to {}", workspace),
+        Event::WindowFocused {
+            window: Some(window),
+        } => println!("window {:#x} focused", window),
+        Event::WindowFocused { window: None } => println!("focus moved to the root window"),
+        Event::WindowTitleChanged { window, title } => {
+            println!("window {:#x} title changed to `{}`", window, title)
+        }
+        Event::LayoutChanged { workspace, layout } => {
+            println!("workspace {} layout changed to {}", workspace, layout)
+        }
+        Event::WindowUrgent {
+            window,
+            workspace,
+            urgent,
+        } => {
+            let state = if *urgent {
+                "is urgent"
+            } else {
+                "is no longer urgent"
+            };
+            println!("window {:#x} on workspace {} {}", window, workspace, state)
+        }
+        Event::ConfigReloaded => println!("config reloaded"),
+    }
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("flowctl: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,276 @@
+/// semantic validation of a config, used by `flow --check-config` to report
+/// every problem at once instead of ignoring the invalid combos at runtime
+use std::path::Path;
+
+use x11rb_async::connection::Connection;
+
+use crate::action::Action;
+use crate::config::{Config, ConfigCombo};
+use crate::key::{Key, KeyPress, KeyState};
+use crate::workspace::WORKSPACES_COUNT;
+
+/// parses the config at the given path and returns the problems found in it,
+/// the built-in config is checked when no path is given, parse errors are
+/// returned as an error, the keys are checked against the keyboard layout
+/// only when a connection is given
+pub async fn check_config<C>(
+    path: Option<&Path>,
+    connection: Option<&C>,
+) -> anyhow::Result<Vec<String>>
+where
+    C: Connection,
+{
+    let config = match path {
+        Some(path) => Config::from_path(path)?,
+        None => Config::builtin(),
+    };
+    let keystate = match connection {
+        Some(connection) => Some(KeyState::from_connection(connection).await?),
+        None => None,
+    };
+    Ok(check(&config, keystate.as_ref())
+        .into_iter()
+        .map(|problem| problem.message)
+        .collect())
+}
+
+/// a problem found in a config with the names of the combos it is about
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Problem {
+    pub combos: Vec<String>,
+    pub message: String,
+}
+
+impl Problem {
+    fn new(combos: &[&str], message: String) -> Self {
+        Self {
+            combos: combos.iter().map(|name| name.to_string()).collect(),
+            message,
+        }
+    }
+}
+
+/// checks the combos of the config, the keys are checked against
+/// the keyboard layout only when a keystate is given
+pub(crate) fn check(config: &Config, keystate: Option<&KeyState>) -> Vec<Problem> {
+    let mut names: Vec<&String> = config.combos().keys().collect();
+    names.sort();
+
+    let mut problems = Vec::new();
+    let mut combos: Vec<(&str, Vec<Key>)> = Vec::with_capacity(names.len());
+    for name in names {
+        let combo = &config.combos()[name];
+        check_handler(name, combo, &mut problems);
+
+        if combo.keys().is_empty() {
+            problems.push(Problem::new(
+                &[name],
+                format!("combo `{}` has no keys", name),
+            ));
+            continue;
+        }
+
+        let mut keys = Vec::with_capacity(combo.keys().len());
+        for key in combo.keys() {
+            match key.parse::<Key>() {
+                Ok(parsed) => {
+                    if let Some(keystate) = keystate
+                        && parsed.keycode(keystate).is_none()
+                    {
+                        problems.push(Problem::new(
+                            &[name],
+                            format!(
+                                "combo `{}` key `{}` has no keycode in the keyboard layout",
+                                name, key
+                            ),
+                        ));
+                    }
+                    keys.push(parsed);
+                }
+                Err(err) => problems.push(Problem::new(
+                    &[name],
+                    format!("combo `{}` key `{}`, {}", name, key, err),
+                )),
+            }
+        }
+
+        if keys.len() == combo.keys().len() {
+            combos.push((name, keys));
+        }
+    }
+
+    // the combos are told apart by the key presses the keys resolve to, so
+    // keys like `q` and `w` on the same keycode are found as well, without a
+    // keyboard layout the keys themselves are compared
+    match keystate {
+        Some(keystate) => {
+            let presses: Vec<(&str, Vec<KeyPress>)> = combos
+                .iter()
+                .filter_map(|(name, keys)| {
+                    let presses = keys.iter().map(|key| key.press(keystate));
+                    Some((*name, presses.collect::<Option<_>>()?))
+                })
+                .collect();
+            check_conflicts(&presses, &mut problems);
+        }
+        None => check_conflicts(&combos, &mut problems),
+    }
+    problems
+}
+
+/// a combo that starts with all the keys of another combo can never be
+/// reached in a consistent way, the same goes for two identical combos
+fn check_conflicts<K: PartialEq>(combos: &[(&str, Vec<K>)], problems: &mut Vec<Problem>) {
+    for (index, (name, keys)) in combos.iter().enumerate() {
+        for (other, other_keys) in &combos[index + 1..] {
+            if keys == other_keys {
+                problems.push(Problem::new(
+                    &[name, other],
+                    format!("combos `{}` and `{}` have the same keys", name, other),
+                ));
+            } else if keys.starts_with(other_keys) || other_keys.starts_with(keys) {
+                problems.push(Problem::new(
+                    &[name, other],
+                    format!(
+                        "combos `{}` and `{}` conflict, the keys of one are a prefix of the other",
+                        name, other
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+fn check_handler(name: &str, combo: &ConfigCombo, problems: &mut Vec<Problem>) {
+    let message = match (combo.spawn(), combo.action()) {
+        (Some(spawn), None) if spawn.program_and_args().is_none() => {
+            format!("combo `{}` has an empty `spawn` list", name)
+        }
+        (None, Some(Action::ViewWorkspace(n) | Action::MoveToWorkspace(n)))
+            if !(1..=WORKSPACES_COUNT).contains(n) =>
+        {
+            format!(
+                "combo `{}` uses workspace {}, workspaces are numbered 1 to {}",
+                name, n, WORKSPACES_COUNT
+            )
+        }
+        (Some(_), Some(_)) => format!("combo `{}` defines both `spawn` and `action`", name),
+        (None, None) => format!("combo `{}` defines neither `spawn` nor `action`", name),
+        _ => return,
+    };
+    problems.push(Problem::new(&[name], message));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn problems(config: &str) -> Vec<Problem> {
+        let config: Config = toml::from_str(config).unwrap();
+        check(&config, None)
+    }
+
+    #[test]
+    fn reports_workspaces_out_of_range() {
+        let problems = problems(
+            r#"
+            [combo.workspace]
+            keys = ["0"]
+            action = { view-workspace = 0 }
+            "#,
+        );
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].combos, vec!["workspace"]);
+    }
+
+    #[test]
+    fn reports_invalid_handlers() {
+        let problems = problems(
+            r#"
+            [combo.both]
+            keys = ["a"]
+            spawn = "xterm"
+            action = "close"
+
+            [combo.neither]
+            keys = ["b"]
+
+            [combo.empty]
+            keys = ["c"]
+            spawn = []
+            "#,
+        );
+        let combos: Vec<_> = problems.iter().map(|p| p.combos.clone()).collect();
+        assert_eq!(combos, vec![vec!["both"], vec!["empty"], vec!["neither"]]);
+    }
+
+    #[test]
+    fn reports_invalid_keys() {
+        let problems = problems(
+            r#"
+            [combo.nothing]
+            keys = []
+            action = "close"
+
+            [combo.unknown]
+            keys = ["Hyper+q"]
+            action = "close"
+            "#,
+        );
+        let combos: Vec<_> = problems.iter().map(|p| p.combos.clone()).collect();
+        assert_eq!(combos, vec![vec!["nothing"], vec!["unknown"]]);
+    }
+
+    #[test]
+    fn reports_conflicting_keys() {
+        let problems = problems(
+            r#"
+            [combo.a]
+            keys = ["q"]
+            action = "close"
+
+            [combo.b]
+            keys = ["q"]
+            action = "quit"
+
+            [combo.c]
+            keys = ["q", "w"]
+            action = "reload"
+
+            [combo.d]
+            keys = ["Shift+q"]
+            action = "reload"
+            "#,
+        );
+        let combos: Vec<_> = problems.iter().map(|p| p.combos.clone()).collect();
+        assert_eq!(combos, vec![vec!["a", "b"], vec!["a", "c"], vec!["b", "c"]]);
+        assert!(problems[0].message.contains("same keys"));
+        assert!(problems[1].message.contains("prefix"));
+    }
+
+    #[test]
+    fn reports_keys_on_the_same_keycode() {
+        use xkbcommon::xkb::Keysym;
+
+        // a single keycode with `q` in the first group and `w` in the second
+        let keysyms = [Keysym::q, Keysym::Q, Keysym::w, Keysym::W];
+        let keystate = KeyState::new(8, 8, 4, keysyms.iter().map(|k| k.raw()).collect());
+        let config: Config = toml::from_str(
+            r#"
+            [combo.a]
+            keys = ["q"]
+            action = "close"
+
+            [combo.b]
+            keys = ["w"]
+            action = "quit"
+            "#,
+        )
+        .unwrap();
+
+        assert!(check(&config, None).is_empty());
+        let problems = check(&config, Some(&keystate));
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].combos, vec!["a", "b"]);
+    }
+}
@@ -0,0 +1,36 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use x11rb_async::connection::Connection;
+
+use super::ComboHandler;
+use crate::action::Action;
+use crate::wm::WindowManager;
+
+/// builtin handler executes one of the window manager
+/// built-in actions, like switching workspaces
+#[derive(Debug)]
+pub struct Builtin {
+    name: String,
+    action: Action,
+}
+
+impl Builtin {
+    pub fn new(name: String, action: Action) -> Self {
+        Self { name, action }
+    }
+}
+
+#[async_trait]
+impl<C> ComboHandler<C> for Builtin
+where
+    C: Connection + Sync + Send + 'static,
+{
+    fn handler_name(&self) -> &str {
+        &self.name
+    }
+
+    async fn handle(&self, wm: Arc<WindowManager<C>>) -> anyhow::Result<()> {
+        wm.execute(&self.action).await
+    }
+}
@@ -1,16 +1,27 @@
+use std::sync::Arc;
+
 use async_trait::async_trait;
+use x11rb_async::connection::Connection;
+
+use crate::wm::WindowManager;
 
 mod spawn;
+mod builtin;
 
 pub use spawn::Spawn;
+pub use builtin::Builtin;
 
 /// a trait that is implemented on types that can be used
 /// as handlers for key combo
 #[async_trait]
-pub trait ComboHandler: Send + Sync {
+pub trait ComboHandler<C>: Send + Sync
+where
+    C: Connection + Sync + Send + 'static,
+{
     fn handler_name(&self) -> &str;
 
     /// the handler will be called by the combo executer
-    /// to execute the handler logic
-    async fn handle(&self) -> anyhow::Result<()>;
+    /// to execute the handler logic, the window manager is given
+    /// so handlers can operate on its state
+    async fn handle(&self, wm: Arc<WindowManager<C>>) -> anyhow::Result<()>;
 }
@@ -1,7 +1,13 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
 use std::process::Stdio;
+use std::sync::Arc;
 
 use async_trait::async_trait;
+use x11rb_async::connection::Connection;
+
 use super::ComboHandler;
+use crate::wm::WindowManager;
 
 /// spawn handler allows calling subprocesses to be executed
 /// so binding combos will trigger subprocess that can
@@ -11,6 +17,8 @@ pub struct Spawn {
     name: String,
     program: String,
     arguments: Vec<String>,
+    env: HashMap<String, String>,
+    cwd: Option<PathBuf>,
 }
 
 impl Spawn {
@@ -19,26 +27,54 @@ impl Spawn {
             name,
             program,
             arguments,
+            ..Default::default()
         }
     }
-}
 
-#[async_trait]
-impl ComboHandler for Spawn {
-    fn handler_name(&self) -> &str {
-        &self.name
+    /// sets extra environment variables for the spawned process, those
+    /// are added on top of the window manager environment
+    pub fn with_env(mut self, env: HashMap<String, String>) -> Self {
+        self.env = env;
+        self
+    }
+
+    /// sets the working directory the process will be spawned in
+    pub fn with_cwd(mut self, cwd: Option<PathBuf>) -> Self {
+        self.cwd = cwd;
+        self
     }
 
     /// fire off the given program with the given argument
     /// if there is a problem with the spawning the process we return it, but we don't
     /// care about the process results itself
-    async fn handle(&self) -> anyhow::Result<()> {
-        tokio::process::Command::new(&self.program)
+    pub fn spawn(&self) -> anyhow::Result<()> {
+        let mut command = tokio::process::Command::new(&self.program);
+        command
             .args(&self.arguments)
+            .envs(&self.env)
             .stdin(Stdio::null())
             .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .spawn()?;
+            .stderr(Stdio::null());
+
+        if let Some(cwd) = &self.cwd {
+            command.current_dir(cwd);
+        }
+
+        command.spawn()?;
         Ok(())
     }
 }
+
+#[async_trait]
+impl<C> ComboHandler<C> for Spawn
+where
+    C: Connection + Sync + Send + 'static,
+{
+    fn handler_name(&self) -> &str {
+        &self.name
+    }
+
+    async fn handle(&self, _wm: Arc<WindowManager<C>>) -> anyhow::Result<()> {
+        self.spawn()
+    }
+}
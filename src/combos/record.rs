@@ -5,6 +5,8 @@
 use std::fmt;
 use xkbcommon::xkb;
 
+use crate::key::KeyPress;
+
 /// user pressed combination can change very fast
 /// and we want to take a snapshot of the current pressed combinations
 /// to do stuff with it, but we don't want to block the user from pressing more
@@ -15,14 +17,14 @@ use xkbcommon::xkb;
 /// with different combinations
 #[derive(Debug)]
 #[repr(transparent)]
-pub struct ComboSnapshot(Vec<xkb::Keycode>);
+pub struct ComboSnapshot(Vec<KeyPress>);
 
 impl fmt::Display for ComboSnapshot {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
         let combo_string = self
             .0
             .iter()
-            .map(|k| k.raw().to_string())
+            .map(|press| press.to_string())
             .collect::<Vec<String>>()
             .join("+");
         write!(fmt, "{}", combo_string)
@@ -30,8 +32,8 @@ impl fmt::Display for ComboSnapshot {
 }
 
 impl IntoIterator for ComboSnapshot {
-    type Item = xkb::Keycode;
-    type IntoIter = <Vec<xkb::Keycode> as IntoIterator>::IntoIter;
+    type Item = KeyPress;
+    type IntoIter = <Vec<KeyPress> as IntoIterator>::IntoIter;
 
     fn into_iter(self) -> Self::IntoIter {
         self.0.into_iter()
@@ -40,22 +42,22 @@ impl IntoIterator for ComboSnapshot {
 
 #[derive(Debug)]
 #[repr(transparent)]
-pub struct ComboRecord(Vec<xkb::Keycode>);
+pub struct ComboRecord(Vec<KeyPress>);
 
 impl ComboRecord {
-    /// pushes the given keycode to the end of the combo
+    /// pushes the given key press to the end of the combo
     /// since this the given keycode is
-    pub fn add(&mut self, keycode: xkb::Keycode) {
-        if !self.0.contains(&keycode) {
-            self.0.push(keycode);
+    pub fn add(&mut self, press: KeyPress) {
+        if !self.0.iter().any(|p| p.keycode() == press.keycode()) {
+            self.0.push(press);
         }
     }
 
     /// remove the given keycode from the combo, if the keycode somehow
     /// does not exists nothing will be done
     pub fn remove(&mut self, keycode: xkb::Keycode) {
-        let index = self.0.iter().enumerate().find_map(|(i, k)| {
-            if *k == keycode {
+        let index = self.0.iter().enumerate().find_map(|(i, p)| {
+            if p.keycode() == keycode {
                 return Some(i);
             }
             None
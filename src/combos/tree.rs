@@ -1,27 +1,45 @@
 use std::sync::Arc;
 use std::collections::HashMap;
-use xkbcommon::xkb;
-
+use x11rb_async::connection::Connection;
 use super::handlers::ComboHandler;
+use crate::key::KeyPress;
 
-#[derive(Default)]
-struct Combo {
-    entries: HashMap<xkb::Keycode, Combo>,
+struct Combo<C>
+where
+    C: Connection + Sync + Send + 'static,
+{
+    entries: HashMap<KeyPress, Combo<C>>,
     /// the handler for the current combination, it is inside
     /// an `Arc` so it can be returned indipendently of the lifetime
     /// of the current Combo
-    handler: Option<Arc<dyn ComboHandler>>,
+    handler: Option<Arc<dyn ComboHandler<C>>>,
+}
+
+// implemented by hand, deriving would require `C: Default`
+impl<C> Default for Combo<C>
+where
+    C: Connection + Sync + Send + 'static,
+{
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+            handler: None,
+        }
+    }
 }
 
-impl Combo {
-    fn add<T>(&mut self, combo: &[T], handler: Arc<dyn ComboHandler>)
+impl<C> Combo<C>
+where
+    C: Connection + Sync + Send + 'static,
+{
+    fn add<T>(&mut self, combo: &[T], handler: Arc<dyn ComboHandler<C>>)
     where
-        T: Into<xkb::Keycode> + Clone,
+        T: Into<KeyPress> + Clone,
     {
         match combo.first().cloned() {
-            Some(keycode) => self
+            Some(press) => self
                 .entries
-                .entry(keycode.clone().into())
+                .entry(press.into())
                 .or_default()
                 .add(&combo[1..], handler),
             // if there is not next char in the combo, it means the current
@@ -34,17 +52,17 @@ impl Combo {
 
     /// drills down the `entries` to the last `Combo`, when last combo is reached
     /// the iterator will be empty and the combo should return its handler
-    fn find<I, T>(&self, mut combo: I) -> Option<Arc<dyn ComboHandler>>
+    fn find<I, T>(&self, mut combo: I) -> Option<Arc<dyn ComboHandler<C>>>
     where
         I: Iterator<Item = T>,
-        T: Into<xkb::Keycode>,
+        T: Into<KeyPress>,
     {
         match combo.next() {
-            Some(keycode) => self
+            Some(press) => self
                 .entries
-                .get(&keycode.into())
+                .get(&press.into())
                 .and_then(|bind| bind.find(combo)),
-            None => self.handler.as_ref().map(|handler| Arc::clone(handler)),
+            None => self.handler.as_ref().map(Arc::clone),
         }
     }
 }
@@ -52,18 +70,34 @@ impl Combo {
 /// the `BindsTree` type holds key combo bind information, since x11
 /// use keycodes to signal what key was pressed, `BindsTree` also uses keycodes
 /// for combinations, if key we want
-#[derive(Default)]
-pub struct ComboTree {
-    root: Combo,
+pub struct ComboTree<C>
+where
+    C: Connection + Sync + Send + 'static,
+{
+    root: Combo<C>,
+}
+
+impl<C> Default for ComboTree<C>
+where
+    C: Connection + Sync + Send + 'static,
+{
+    fn default() -> Self {
+        Self {
+            root: Combo::default(),
+        }
+    }
 }
 
-impl ComboTree {
+impl<C> ComboTree<C>
+where
+    C: Connection + Sync + Send + 'static,
+{
     /// takes a combination of keycode arguments with the handler that should be called
     /// when the combination performed
     #[inline]
-    pub fn add_combo<T>(&mut self, combo: &[T], handler: Arc<dyn ComboHandler>)
+    pub fn add_combo<T>(&mut self, combo: &[T], handler: Arc<dyn ComboHandler<C>>)
     where
-        T: Into<xkb::Keycode> + Clone,
+        T: Into<KeyPress> + Clone,
     {
         self.root.add(combo, handler)
     }
@@ -71,17 +105,11 @@ impl ComboTree {
     /// returns the handler for the provided combo, if `None` is returned
     /// it means that the given combination wasn't registered
     #[inline]
-    pub fn find_combo_handler<I, T>(&self, combo: I) -> Option<Arc<dyn ComboHandler>>
+    pub fn find_combo_handler<I, T>(&self, combo: I) -> Option<Arc<dyn ComboHandler<C>>>
     where
         I: IntoIterator<Item = T>,
-        T: Into<xkb::Keycode>,
+        T: Into<KeyPress>,
     {
         self.root.find(combo.into_iter())
     }
-
-    /// clears all registered combinations in the bind tree
-    #[inline]
-    pub fn clear(&mut self) {
-        self.root.entries.drain();
-    }
 }
@@ -5,12 +5,20 @@
 /// or the user doesn't have config at all
 use std::collections::HashMap;
 use std::fs::read_to_string;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
 
-use serde::Deserialize;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
 
 use x11rb_async::protocol::xproto::ModMask;
 
+use crate::action::Action;
+use crate::layout::LayoutKind;
+use crate::logger::{LogFilter, LogFormat};
+
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Deserialize, Default, Clone, Copy)]
 pub enum Modifier {
     CTRL,
@@ -24,6 +32,26 @@ pub enum Modifier {
     M5,
 }
 
+impl FromStr for Modifier {
+    type Err = anyhow::Error;
+
+    /// parses the modifiers written inside combo keys, the names are case
+    /// insensitive and the common `Alt` and `Super` names are accepted
+    fn from_str(value: &str) -> anyhow::Result<Modifier> {
+        match value.to_lowercase().as_str() {
+            "ctrl" | "control" => Ok(Modifier::CTRL),
+            "shift" => Ok(Modifier::SHIFT),
+            "lock" => Ok(Modifier::LOCK),
+            "m1" | "mod1" | "alt" => Ok(Modifier::M1),
+            "m2" | "mod2" => Ok(Modifier::M2),
+            "m3" | "mod3" => Ok(Modifier::M3),
+            "m4" | "mod4" | "super" => Ok(Modifier::M4),
+            "m5" | "mod5" => Ok(Modifier::M5),
+            _ => Err(anyhow::anyhow!("unknown modifier `{}`", value)),
+        }
+    }
+}
+
 impl From<Modifier> for ModMask {
     fn from(value: Modifier) -> ModMask {
         match value {
@@ -39,16 +67,93 @@ impl From<Modifier> for ModMask {
     }
 }
 
+/// the command a combo spawns, can be written as a list
+/// of the program and its arguments, or as a single string that
+/// will be passed to `sh -c`
+/// ```toml
+/// spawn = ["alacritty", "-e", "htop"]
+/// spawn = "rofi -show run || dmenu_run"
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum SpawnCommand {
+    Shell(String),
+    Exec(Vec<String>),
+}
+
+impl SpawnCommand {
+    /// returns the program to execute and its arguments, a shell
+    /// string is wrapped with `sh -c`, returns `None` if the
+    /// command is empty
+    pub fn program_and_args(&self) -> Option<(String, Vec<String>)> {
+        match self {
+            SpawnCommand::Shell(command) if command.trim().is_empty() => None,
+            SpawnCommand::Shell(command) => {
+                Some(("sh".to_string(), vec!["-c".to_string(), command.clone()]))
+            }
+            SpawnCommand::Exec(command) => command
+                .split_first()
+                .map(|(program, args)| (program.clone(), args.to_vec())),
+        }
+    }
+}
+
+/// how windows get the focus with the mouse, windows can
+/// always be focused with combos regardless of the mode
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum FocusMode {
+    /// a window is focused when it is clicked
+    #[default]
+    Click,
+
+    /// a window is focused when the mouse enters it
+    Mouse,
+}
+
+/// what happens when an application asks for one of its windows to be
+/// activated through `_NET_ACTIVE_WINDOW`, requests from pagers and
+/// taskbars are always honored
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ActivationPolicy {
+    /// the window workspace is shown and the window is focused
+    #[default]
+    Focus,
+
+    /// the window is marked as urgent and keeps its place
+    MarkUrgent,
+
+    /// the request is ignored
+    Ignore,
+}
+
 /// a combo definition contains the `keys` (key combination)
-/// and the action to take when the combination is executed
+/// and the action to take when the combination is executed, a combo
+/// either `spawn`s a process or performs a built-in `action`
 ///
 /// all combination has a "hidden" key at the start, that key is the modifier
 /// defined in the `FlowConfig`, so if a combination need to be executed
 /// the user must first press the `modifier` key and then the combination
 #[derive(Debug, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
 pub struct ConfigCombo {
     keys: Vec<String>,
-    spawn: Vec<String>,
+
+    #[serde(default)]
+    spawn: Option<SpawnCommand>,
+
+    #[serde(default)]
+    action: Option<Action>,
+
+    /// extra environment variables set for the spawned process
+    #[serde(default)]
+    env: HashMap<String, String>,
+
+    /// the working directory of the spawned process, if not
+    /// set the process inherits the window manager directory
+    #[serde(default)]
+    cwd: Option<PathBuf>,
 }
 
 impl ConfigCombo {
@@ -58,16 +163,58 @@ impl ConfigCombo {
     }
 
     #[inline]
-    pub fn spawn(&self) -> &[String] {
-        &self.spawn
+    pub fn spawn(&self) -> Option<&SpawnCommand> {
+        self.spawn.as_ref()
+    }
+
+    #[inline]
+    pub fn action(&self) -> Option<&Action> {
+        self.action.as_ref()
+    }
+
+    #[inline]
+    pub fn env(&self) -> &HashMap<String, String> {
+        &self.env
+    }
+
+    #[inline]
+    pub fn cwd(&self) -> Option<&Path> {
+        self.cwd.as_deref()
     }
 }
 
 /// defines the global fields that the flow window manger uses
 /// those fields are too generic to be in specific sections
-#[derive(Debug, Deserialize, Default)]
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct FlowConfig {
     modifier: Modifier,
+    focus: FocusMode,
+
+    /// how activation requests sent by applications are handled
+    activation: ActivationPolicy,
+
+    /// how many milliseconds a window has to close itself after it was
+    /// asked to, before its client is killed, 0 never kills the client
+    close_timeout_ms: u64,
+
+    /// reload the config automatically when the config file changes
+    watch_config: bool,
+
+    /// the log levels, like `info,flow::wm=debug`, the `--log-level`
+    /// argument and the `FLOW_LOG` environment variable take precedence,
+    /// the log settings are only read when flow starts
+    log_level: Option<LogFilter>,
+    log_format: LogFormat,
+
+    /// print the log lines to stdout on top of the log file
+    log_stdout: bool,
+
+    /// the size in kilobytes the log file can reach before it is rotated
+    log_max_size_kb: u64,
+
+    /// how many rotated log files are kept
+    log_keep: usize,
 }
 
 impl FlowConfig {
@@ -75,6 +222,122 @@ impl FlowConfig {
     pub fn modifier(&self) -> Modifier {
         self.modifier
     }
+
+    #[inline]
+    pub fn focus(&self) -> FocusMode {
+        self.focus
+    }
+
+    #[inline]
+    pub fn activation(&self) -> ActivationPolicy {
+        self.activation
+    }
+
+    /// the time before a client that didn't close is killed, `None` if
+    /// clients are never killed
+    #[inline]
+    pub fn close_timeout(&self) -> Option<Duration> {
+        (self.close_timeout_ms > 0).then(|| Duration::from_millis(self.close_timeout_ms))
+    }
+
+    #[inline]
+    pub fn watch_config(&self) -> bool {
+        self.watch_config
+    }
+
+    #[inline]
+    pub fn log_level(&self) -> Option<&LogFilter> {
+        self.log_level.as_ref()
+    }
+
+    #[inline]
+    pub fn log_format(&self) -> LogFormat {
+        self.log_format
+    }
+
+    #[inline]
+    pub fn log_stdout(&self) -> bool {
+        self.log_stdout
+    }
+
+    /// the log file size in bytes that triggers a rotation
+    #[inline]
+    pub fn log_max_size(&self) -> u64 {
+        self.log_max_size_kb.saturating_mul(1024)
+    }
+
+    #[inline]
+    pub fn log_keep(&self) -> usize {
+        self.log_keep
+    }
+}
+
+impl Default for FlowConfig {
+    fn default() -> Self {
+        Self {
+            modifier: Modifier::default(),
+            focus: FocusMode::default(),
+            activation: ActivationPolicy::default(),
+            close_timeout_ms: 0,
+            watch_config: false,
+            log_level: None,
+            log_format: LogFormat::default(),
+            log_stdout: false,
+            log_max_size_kb: 10 * 1024,
+            log_keep: 5,
+        }
+    }
+}
+
+/// the `[layout]` section, configures how the windows of
+/// a workspace are tiled on the screen
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LayoutConfig {
+    /// the layout new workspaces start with
+    default: LayoutKind,
+
+    /// how many windows are placed in the master column
+    master_count: usize,
+
+    /// the fraction of the screen width the master column takes
+    master_ratio: f32,
+
+    /// the border width in pixels drawn around each window
+    border_width: u32,
+}
+
+impl LayoutConfig {
+    #[inline]
+    pub fn default_layout(&self) -> LayoutKind {
+        self.default
+    }
+
+    #[inline]
+    pub fn master_count(&self) -> usize {
+        self.master_count
+    }
+
+    #[inline]
+    pub fn master_ratio(&self) -> f32 {
+        self.master_ratio
+    }
+
+    #[inline]
+    pub fn border_width(&self) -> u32 {
+        self.border_width
+    }
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        Self {
+            default: LayoutKind::default(),
+            master_count: 1,
+            master_ratio: 0.55,
+            border_width: 4,
+        }
+    }
 }
 
 /// represent the fields and sections that the config file
@@ -86,29 +349,53 @@ impl FlowConfig {
 /// easier / cleaner config file, not requiring to hussle with config
 /// options and just start
 #[derive(Debug, Deserialize, Default)]
-#[serde(default)]
+#[serde(default, deny_unknown_fields)]
 pub struct Config {
     flow: FlowConfig,
+    layout: LayoutConfig,
 
     /// the defined combos with names in the config file
     /// the hashkey is the name, the value is the combo information
     /// ```toml
-    /// [combos.name]
+    /// [combo.name]
     /// ...
     /// ```
     #[serde(rename(deserialize = "combo"))]
     combos: HashMap<String, ConfigCombo>,
 }
 
+/// the commented config flow uses when no config file is found
+pub const DEFAULT_CONFIG: &str = include_str!("default.toml");
+
 impl Config {
+    /// the built-in config parsed from `DEFAULT_CONFIG`, unlike `Default`
+    /// which leaves the combos empty it binds the essential combos
+    pub fn builtin() -> Self {
+        // the built-in config is part of the binary, failing to parse it is a bug
+        toml::from_str(DEFAULT_CONFIG).expect("the built-in config is valid")
+    }
+
     /// read the file content located at the given path and using
-    /// a toml parser to parse the data to rust representation
+    /// a toml parser to parse the data to rust representation, parse
+    /// errors are reported as `path:line:column: message`
     pub fn from_path<P>(path: P) -> anyhow::Result<Self>
     where
         P: AsRef<Path>,
     {
-        let content = read_to_string(path)?;
-        Ok(toml::from_str(&content)?)
+        let path = path.as_ref();
+        let content = read_to_string(path)
+            .with_context(|| format!("couldn't read config file `{}`", path.display()))?;
+
+        toml::from_str(&content).map_err(|err| {
+            let message = err.message().trim_end();
+            match err.span() {
+                Some(span) => {
+                    let (line, column) = line_column(&content, span.start);
+                    anyhow::anyhow!("{}:{}:{}: {}", path.display(), line, column, message)
+                }
+                None => anyhow::anyhow!("{}: {}", path.display(), message),
+            }
+        })
     }
 
     #[inline]
@@ -116,6 +403,11 @@ impl Config {
         &self.flow
     }
 
+    #[inline]
+    pub fn layout(&self) -> &LayoutConfig {
+        &self.layout
+    }
+
     /// returns the configured combos as hashmaps, the key is the bind name
     /// while the value is the config bind
     #[inline]
@@ -123,3 +415,47 @@ impl Config {
         &self.combos
     }
 }
+
+/// returns the 1-based line and column of the given byte offset
+fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rsplit('\n')
+        .next()
+        .map_or(0, |line| line.chars().count())
+        + 1;
+    (line, column)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_builtin_config_parses() {
+        let config = Config::builtin();
+        assert!(!config.combos().is_empty());
+        assert_eq!(config.flow().close_timeout(), None);
+        assert_eq!(config.layout().default_layout(), LayoutKind::MasterStack);
+    }
+
+    #[test]
+    fn the_builtin_config_has_no_problems() {
+        assert_eq!(crate::check::check(&Config::builtin(), None), Vec::new());
+    }
+
+    #[test]
+    fn an_empty_config_uses_the_defaults() {
+        let config: Config = toml::from_str("").unwrap();
+        assert!(config.combos().is_empty());
+        assert_eq!(config.layout().border_width(), 4);
+    }
+
+    #[test]
+    fn finds_the_line_and_column() {
+        assert_eq!(line_column("ab\ncd", 0), (1, 1));
+        assert_eq!(line_column("ab\ncd", 4), (2, 2));
+        assert_eq!(line_column("ab", 10), (1, 3));
+    }
+}
@@ -0,0 +1,405 @@
+/// the extended window manager hints, the root window properties that
+/// tell pagers, bars and tools like `wmctrl` about flow and its windows
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use x11rb_async::connection::Connection;
+use x11rb_async::protocol::xproto::{
+    AtomEnum, ChangeWindowAttributesAux, ConnectionExt as _, CreateWindowAux, EventMask, Window,
+    WindowClass, WindowEnum,
+};
+
+use crate::atoms::Atoms;
+use crate::layout::Geometry;
+use crate::property;
+use crate::workspace::WORKSPACES_COUNT;
+
+pub struct Ewmh<C>
+where
+    C: Connection,
+{
+    connection: Arc<C>,
+    root: Window,
+    atoms: Atoms,
+
+    /// the `_NET_SUPPORTING_WM_CHECK` window, it is never mapped
+    check_window: Window,
+
+    /// the managed windows in the order they were managed
+    clients: Mutex<Vec<Window>>,
+
+    /// the managed windows from the bottom of the stack to its top,
+    /// flow raises windows only when they are focused
+    stacking: Mutex<Vec<Window>>,
+
+    /// the dock windows flow shows without managing them,
+    /// with the space they reserve at the screen edges
+    docks: Mutex<HashMap<Window, Strut>>,
+}
+
+/// the space a dock reserves at each screen edge, in pixels
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Strut {
+    pub left: u32,
+    pub right: u32,
+    pub top: u32,
+    pub bottom: u32,
+}
+
+impl Strut {
+    /// reads the `_NET_WM_STRUT_PARTIAL` of the window, or the older
+    /// `_NET_WM_STRUT`, both start with the left, right, top and bottom
+    /// widths, the ranges of the partial strut are ignored since flow
+    /// manages a single screen, the widths are clamped to the screen size
+    pub async fn read<C>(
+        connection: &C,
+        window: Window,
+        atoms: &Atoms,
+        screen: Geometry,
+    ) -> anyhow::Result<Self>
+    where
+        C: Connection,
+    {
+        let mut values = property::get_u32(
+            connection,
+            window,
+            atoms._NET_WM_STRUT_PARTIAL,
+            AtomEnum::CARDINAL.into(),
+        )
+        .await?;
+        if values.len() < 4 {
+            values = property::get_u32(
+                connection,
+                window,
+                atoms._NET_WM_STRUT,
+                AtomEnum::CARDINAL.into(),
+            )
+            .await?;
+        }
+
+        Ok(match values[..] {
+            [left, right, top, bottom, ..] => Self {
+                left: left.min(screen.width),
+                right: right.min(screen.width),
+                top: top.min(screen.height),
+                bottom: bottom.min(screen.height),
+            },
+            _ => Self::default(),
+        })
+    }
+
+    /// the largest space reserved at each edge by either strut
+    fn max(self, other: Strut) -> Strut {
+        Strut {
+            left: self.left.max(other.left),
+            right: self.right.max(other.right),
+            top: self.top.max(other.top),
+            bottom: self.bottom.max(other.bottom),
+        }
+    }
+
+    /// the part of the screen that is left once the strut is reserved
+    pub fn shrink(self, screen: Geometry) -> Geometry {
+        let horizontal = self.left.saturating_add(self.right);
+        let vertical = self.top.saturating_add(self.bottom);
+        let left = self.left.min(screen.width.saturating_sub(1));
+        let top = self.top.min(screen.height.saturating_sub(1));
+        Geometry::new(
+            screen.x.saturating_add_unsigned(left),
+            screen.y.saturating_add_unsigned(top),
+            screen.width.saturating_sub(horizontal).max(1),
+            screen.height.saturating_sub(vertical).max(1),
+        )
+    }
+}
+
+impl<C> Ewmh<C>
+where
+    C: Connection,
+{
+    /// creates the supporting check window and advertises
+    /// the supported hints on the root window
+    pub async fn setup(connection: Arc<C>, root: Window, atoms: Atoms) -> anyhow::Result<Self> {
+        let check_window = connection.generate_id().await?;
+        connection
+            .create_window(
+                0,
+                check_window,
+                root,
+                -1,
+                -1,
+                1,
+                1,
+                0,
+                WindowClass::INPUT_ONLY,
+                0,
+                &CreateWindowAux::new().override_redirect(1),
+            )
+            .await?
+            .check()
+            .await?;
+
+        // the check window points to itself, so clients can tell
+        // the root property doesn't reference a stale window
+        for window in [root, check_window] {
+            property::set_u32(
+                &*connection,
+                window,
+                atoms._NET_SUPPORTING_WM_CHECK,
+                AtomEnum::WINDOW.into(),
+                &[check_window],
+            )
+            .await?;
+        }
+        property::set_string(
+            &*connection,
+            check_window,
+            atoms._NET_WM_NAME,
+            atoms.UTF8_STRING,
+            "flow",
+        )
+        .await?;
+
+        let supported = [
+            atoms._NET_SUPPORTED,
+            atoms._NET_SUPPORTING_WM_CHECK,
+            atoms._NET_WM_NAME,
+            atoms._NET_CLIENT_LIST,
+            atoms._NET_CLIENT_LIST_STACKING,
+            atoms._NET_NUMBER_OF_DESKTOPS,
+            atoms._NET_DESKTOP_NAMES,
+            atoms._NET_CURRENT_DESKTOP,
+            atoms._NET_WM_DESKTOP,
+            atoms._NET_ACTIVE_WINDOW,
+            atoms._NET_WM_STATE,
+            atoms._NET_WM_STATE_FULLSCREEN,
+            atoms._NET_WM_STATE_DEMANDS_ATTENTION,
+            atoms._NET_WM_WINDOW_TYPE,
+            atoms._NET_WM_STRUT,
+            atoms._NET_WM_STRUT_PARTIAL,
+            atoms._NET_WM_WINDOW_TYPE_NORMAL,
+            atoms._NET_WM_WINDOW_TYPE_DOCK,
+            atoms._NET_WM_WINDOW_TYPE_DIALOG,
+            atoms._NET_WM_WINDOW_TYPE_UTILITY,
+            atoms._NET_WM_WINDOW_TYPE_SPLASH,
+            atoms._NET_WM_WINDOW_TYPE_NOTIFICATION,
+            atoms._NET_WM_WINDOW_TYPE_TOOLTIP,
+        ];
+        property::set_u32(
+            &*connection,
+            root,
+            atoms._NET_SUPPORTED,
+            AtomEnum::ATOM.into(),
+            &supported,
+        )
+        .await?;
+
+        // the desktops are the flow workspaces, named by their number
+        property::set_u32(
+            &*connection,
+            root,
+            atoms._NET_NUMBER_OF_DESKTOPS,
+            AtomEnum::CARDINAL.into(),
+            &[WORKSPACES_COUNT as u32],
+        )
+        .await?;
+        let names: String = (1..=WORKSPACES_COUNT)
+            .map(|number| format!("{}\0", number))
+            .collect();
+        property::set_string(
+            &*connection,
+            root,
+            atoms._NET_DESKTOP_NAMES,
+            atoms.UTF8_STRING,
+            &names,
+        )
+        .await?;
+
+        let ewmh = Self {
+            connection,
+            root,
+            atoms,
+            check_window,
+            clients: Mutex::new(Vec::new()),
+            stacking: Mutex::new(Vec::new()),
+            docks: Mutex::new(HashMap::new()),
+        };
+        ewmh.write_client_lists().await?;
+        ewmh.set_current_desktop(0).await?;
+        ewmh.set_active_window(None).await?;
+        Ok(ewmh)
+    }
+
+    /// adds a newly managed window, on top of the stack
+    pub async fn add_client(&self, window: Window) -> anyhow::Result<()> {
+        // the locks are never held across an await, they can't be poisoned
+        {
+            let mut clients = self.clients.lock().unwrap();
+            if clients.contains(&window) {
+                return Ok(());
+            }
+            clients.push(window);
+        }
+
+        self.stacking.lock().unwrap().push(window);
+        self.write_client_lists().await
+    }
+
+    pub async fn remove_client(&self, window: Window) -> anyhow::Result<()> {
+        self.clients.lock().unwrap().retain(|&w| w != window);
+        self.stacking.lock().unwrap().retain(|&w| w != window);
+        self.write_client_lists().await
+    }
+
+    /// moves the window to the top of the stack, after flow raised it
+    pub async fn raise(&self, window: Window) -> anyhow::Result<()> {
+        let stacking = {
+            let mut stacking = self.stacking.lock().unwrap();
+            if stacking.last() == Some(&window) || !stacking.contains(&window) {
+                return Ok(());
+            }
+            stacking.retain(|&w| w != window);
+            stacking.push(window);
+            stacking.clone()
+        };
+
+        self.write(self.atoms._NET_CLIENT_LIST_STACKING, &stacking)
+            .await
+    }
+
+    /// starts tracking the dock and the space it reserves, tracking a dock
+    /// again reads its strut again, the dock property changes are selected
+    /// so strut updates are noticed
+    pub async fn add_dock(&self, window: Window, screen: Geometry) -> anyhow::Result<()> {
+        self.connection
+            .change_window_attributes(
+                window,
+                &ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+            )
+            .await?;
+        let strut = Strut::read(&*self.connection, window, &self.atoms, screen).await?;
+        log::debug!("dock {} reserves {:?}", window, strut);
+        self.docks.lock().unwrap().insert(window, strut);
+        Ok(())
+    }
+
+    /// stops tracking the dock, returns false if the window isn't a dock
+    pub fn remove_dock(&self, window: Window) -> bool {
+        self.docks.lock().unwrap().remove(&window).is_some()
+    }
+
+    #[inline]
+    pub fn is_dock(&self, window: Window) -> bool {
+        self.docks.lock().unwrap().contains_key(&window)
+    }
+
+    pub fn docks(&self) -> Vec<Window> {
+        self.docks.lock().unwrap().keys().copied().collect()
+    }
+
+    /// the part of the screen the docks leave for the other windows
+    pub fn work_area(&self, screen: Geometry) -> Geometry {
+        self.docks
+            .lock()
+            .unwrap()
+            .values()
+            .fold(Strut::default(), |reserved, &strut| reserved.max(strut))
+            .shrink(screen)
+    }
+
+    /// publishes the index of the shown workspace
+    pub async fn set_current_desktop(&self, index: usize) -> anyhow::Result<()> {
+        property::set_u32(
+            &*self.connection,
+            self.root,
+            self.atoms._NET_CURRENT_DESKTOP,
+            AtomEnum::CARDINAL.into(),
+            &[index as u32],
+        )
+        .await
+    }
+
+    /// publishes the index of the workspace that holds the window
+    pub async fn set_window_desktop(&self, window: Window, index: usize) -> anyhow::Result<()> {
+        property::set_u32(
+            &*self.connection,
+            window,
+            self.atoms._NET_WM_DESKTOP,
+            AtomEnum::CARDINAL.into(),
+            &[index as u32],
+        )
+        .await
+    }
+
+    /// publishes the focused window, `None` when the focus is on the root
+    pub async fn set_active_window(&self, window: Option<Window>) -> anyhow::Result<()> {
+        property::set_u32(
+            &*self.connection,
+            self.root,
+            self.atoms._NET_ACTIVE_WINDOW,
+            AtomEnum::WINDOW.into(),
+            &[window.unwrap_or(WindowEnum::NONE.into())],
+        )
+        .await
+    }
+
+    async fn write_client_lists(&self) -> anyhow::Result<()> {
+        let clients = self.clients.lock().unwrap().clone();
+        let stacking = self.stacking.lock().unwrap().clone();
+        self.write(self.atoms._NET_CLIENT_LIST, &clients).await?;
+        self.write(self.atoms._NET_CLIENT_LIST_STACKING, &stacking)
+            .await
+    }
+
+    async fn write(&self, property: u32, windows: &[Window]) -> anyhow::Result<()> {
+        property::set_u32(
+            &*self.connection,
+            self.root,
+            property,
+            AtomEnum::WINDOW.into(),
+            windows,
+        )
+        .await
+    }
+
+    /// destroys the check window, so clients don't take
+    /// flow for the running window manager anymore
+    pub async fn release(&self) -> anyhow::Result<()> {
+        self.connection.destroy_window(self.check_window).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCREEN: Geometry = Geometry {
+        x: 0,
+        y: 0,
+        width: 1920,
+        height: 1080,
+    };
+
+    #[test]
+    fn shrinks_the_screen_by_the_strut() {
+        let strut = Strut {
+            left: 0,
+            right: 20,
+            top: 30,
+            bottom: 0,
+        };
+        assert_eq!(strut.shrink(SCREEN), Geometry::new(0, 30, 1900, 1050));
+    }
+
+    #[test]
+    fn bogus_struts_leave_a_valid_area() {
+        let strut = Strut {
+            left: u32::MAX,
+            right: u32::MAX,
+            top: u32::MAX,
+            bottom: 1,
+        };
+        assert_eq!(strut.shrink(SCREEN), Geometry::new(1919, 1079, 1, 1));
+    }
+}
@@ -0,0 +1,124 @@
+use serde::{Deserialize, Serialize};
+
+use crate::action::Action;
+use crate::config::SpawnCommand;
+use crate::layout::{Geometry, LayoutKind};
+
+/// a request sent to the window manager, the requests mirror
+/// what a combo can do in the config file
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub enum Request {
+    /// executes a built-in action
+    Action(Action),
+
+    /// spawns a process, the same as the combo `spawn` field
+    Spawn(SpawnCommand),
+
+    /// returns the workspaces and their windows as a `Tree`
+    Tree,
+
+    /// after the reply, the connection becomes a stream of
+    /// `Event`s, one JSON object per line
+    Subscribe,
+}
+
+/// the reply for a single request, `error` describes why the request
+/// failed when `success` is false, `data` holds the request result
+/// for requests that return one
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Reply {
+    pub success: bool,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+}
+
+impl Reply {
+    pub fn ok() -> Self {
+        Self {
+            success: true,
+            error: None,
+            data: None,
+        }
+    }
+
+    pub fn with_data<T: Serialize>(data: &T) -> Self {
+        match serde_json::to_value(data) {
+            Ok(data) => Self {
+                success: true,
+                error: None,
+                data: Some(data),
+            },
+            Err(err) => Reply::error(err),
+        }
+    }
+
+    pub fn error<E: ToString>(error: E) -> Self {
+        Self {
+            success: false,
+            error: Some(error.to_string()),
+            data: None,
+        }
+    }
+}
+
+/// the state of all the workspaces, returned for the `Tree` request
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Tree {
+    pub workspaces: Vec<WorkspaceTree>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WorkspaceTree {
+    /// the workspace number, starting from 1
+    pub number: usize,
+    pub current: bool,
+    pub layout: LayoutKind,
+
+    /// the id of the focused window in the workspace
+    pub focused: Option<u32>,
+    pub windows: Vec<WindowTree>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WindowTree {
+    pub id: u32,
+    pub title: String,
+    pub geometry: Geometry,
+}
+
+/// the events sent to subscribed clients when
+/// the window manager state changes
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum Event {
+    /// the shown workspace changed, numbered from 1
+    WorkspaceChanged { workspace: usize },
+
+    /// a window got the focus, `None` when the focus
+    /// went to the root window
+    WindowFocused { window: Option<u32> },
+
+    /// a managed window changed its title
+    WindowTitleChanged { window: u32, title: String },
+
+    /// the layout of a workspace changed
+    LayoutChanged {
+        workspace: usize,
+        layout: LayoutKind,
+    },
+
+    /// a managed window set or cleared its urgency hint
+    WindowUrgent {
+        window: u32,
+        workspace: usize,
+        urgent: bool,
+    },
+
+    /// the config file was read again and applied
+    ConfigReloaded,
+}
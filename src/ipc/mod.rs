@@ -0,0 +1,60 @@
+/// the inter process communication subsystem, a running flow instance listens
+/// on a unix socket for line delimited JSON requests, each request is answered
+/// with a single line JSON reply
+///
+/// ```text
+/// -> {"action": {"view-workspace": 3}}
+/// <- {"success": true}
+/// -> {"spawn": ["alacritty", "-e", "htop"]}
+/// <- {"success": true}
+/// -> {"action": {"view-workspace": 12}}
+/// <- {"success": false, "error": "workspace 12 doesn't exist, workspaces are numbered 1 to 9"}
+/// -> "tree"
+/// <- {"success": true, "data": {"workspaces": [...]}}
+/// ```
+///
+/// a client that sends `"subscribe"` gets a reply and then a stream of
+/// events, one per line, until it closes the connection
+///
+/// ```text
+/// -> "subscribe"
+/// <- {"success": true}
+/// <- {"event": "workspace-changed", "workspace": 2}
+/// <- {"event": "window-focused", "window": 4194307}
+/// ```
+use std::env;
+use std::path::PathBuf;
+
+mod message;
+mod server;
+
+pub use message::{Event, Reply, Request, Tree, WindowTree, WorkspaceTree};
+pub use server::IpcServer;
+
+/// returns the socket path of the flow instance that manages the given
+/// display, each display gets its own socket so multiple instances
+/// can run side by side, the socket is placed in `$XDG_RUNTIME_DIR`
+/// or the temporary directory if it isn't set
+pub fn socket_path(display: &str) -> PathBuf {
+    let display: String = display
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    crate::xdg::runtime_dir()
+        .unwrap_or_else(env::temp_dir)
+        .join(format!("flow-{}.sock", display))
+}
+
+/// returns the socket path for the display in the `DISPLAY` environment
+/// variable, the same display the x11 connection uses by default
+pub fn default_socket_path() -> anyhow::Result<PathBuf> {
+    let display = env::var("DISPLAY")
+        .map_err(|_| anyhow::anyhow!("couldn't resolve `DISPLAY` environment variable"))?;
+    Ok(socket_path(&display))
+}
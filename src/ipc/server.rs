@@ -0,0 +1,177 @@
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::Serialize;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::unix::OwnedWriteHalf;
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::broadcast::error::RecvError;
+use x11rb_async::connection::Connection;
+
+use super::{Reply, Request};
+use crate::combos::handlers::Spawn;
+use crate::wm::WindowManager;
+
+/// listens on the unix socket and executes the received requests
+/// on the window manager, the socket file is removed on drop
+pub struct IpcServer {
+    listener: UnixListener,
+    path: PathBuf,
+
+    /// the inode of the socket file, a window manager that replaces
+    /// flow binds its own socket at the same path, which must survive
+    /// this server being dropped
+    inode: u64,
+
+    /// the user that owns the socket, the user flow runs as, any
+    /// other user is refused since requests can spawn processes
+    uid: u32,
+}
+
+impl IpcServer {
+    /// binds the socket at the given path, a socket file left by a previous
+    /// instance that didn't exit cleanly is replaced, only the user flow
+    /// runs as can read and write the socket
+    pub fn bind<P>(path: P) -> anyhow::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref().to_path_buf();
+        if path.exists() {
+            std::fs::remove_file(&path)?;
+        }
+
+        let listener = UnixListener::bind(&path)?;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+        let metadata = std::fs::metadata(&path)?;
+        log::info!("listening for ipc requests on `{}`", path.display());
+        Ok(Self {
+            listener,
+            path,
+            inode: metadata.ino(),
+            uid: metadata.uid(),
+        })
+    }
+
+    /// accepts clients forever, each client is served on its own task
+    pub async fn serve<C>(self, wm: Arc<WindowManager<C>>) -> anyhow::Result<()>
+    where
+        C: Connection + Sync + Send + 'static,
+    {
+        loop {
+            let (stream, _) = self.listener.accept().await?;
+            // the socket mode is set after binding, a client could
+            // connect in between, so the peer user is checked as well
+            match stream.peer_cred() {
+                Ok(cred) if cred.uid() == self.uid => {}
+                Ok(cred) => {
+                    log::warn!("refusing ipc client of user {}", cred.uid());
+                    continue;
+                }
+                Err(err) => {
+                    log::warn!("refusing ipc client, couldn't get its credentials, {}", err);
+                    continue;
+                }
+            }
+            let wm = Arc::clone(&wm);
+            tokio::spawn(async move {
+                if let Err(err) = handle_client(stream, wm).await {
+                    log::error!("ipc client error, {}", err);
+                }
+            });
+        }
+    }
+}
+
+impl Drop for IpcServer {
+    fn drop(&mut self) {
+        if std::fs::metadata(&self.path).is_ok_and(|metadata| metadata.ino() == self.inode) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// reads requests line by line and writes a reply line for each
+/// request, until the client closes the connection
+async fn handle_client<C>(stream: UnixStream, wm: Arc<WindowManager<C>>) -> anyhow::Result<()>
+where
+    C: Connection + Sync + Send + 'static,
+{
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let request = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => request,
+            Err(err) => {
+                write_line(
+                    &mut writer,
+                    &Reply::error(format!("invalid request, {}", err)),
+                )
+                .await?;
+                continue;
+            }
+        };
+
+        log::debug!("ipc request {:?}", request);
+        if let Request::Subscribe = request {
+            write_line(&mut writer, &Reply::ok()).await?;
+            return stream_events(writer, wm).await;
+        }
+
+        let reply = execute(request, &wm).await.unwrap_or_else(Reply::error);
+        write_line(&mut writer, &reply).await?;
+    }
+    Ok(())
+}
+
+/// writes the events to the client until it disconnects, the
+/// client requests are no longer read after it subscribes
+async fn stream_events<C>(
+    mut writer: OwnedWriteHalf,
+    wm: Arc<WindowManager<C>>,
+) -> anyhow::Result<()>
+where
+    C: Connection + Sync + Send + 'static,
+{
+    let mut events = wm.subscribe();
+    loop {
+        match events.recv().await {
+            Ok(event) => write_line(&mut writer, &event).await?,
+            Err(RecvError::Lagged(missed)) => {
+                log::warn!("ipc subscriber is lagging, missed {} events", missed);
+            }
+            Err(RecvError::Closed) => return Ok(()),
+        }
+    }
+}
+
+async fn write_line<T: Serialize>(writer: &mut OwnedWriteHalf, value: &T) -> anyhow::Result<()> {
+    let mut line = serde_json::to_string(value)?;
+    line.push('\n');
+    writer.write_all(line.as_bytes()).await?;
+    Ok(())
+}
+
+async fn execute<C>(request: Request, wm: &Arc<WindowManager<C>>) -> anyhow::Result<Reply>
+where
+    C: Connection + Sync + Send + 'static,
+{
+    match request {
+        Request::Action(action) => wm.execute(&action).await?,
+        Request::Spawn(command) => {
+            let (program, arguments) = command
+                .program_and_args()
+                .ok_or_else(|| anyhow::anyhow!("empty `spawn` command"))?;
+            Spawn::new("ipc".to_string(), program, arguments).spawn()?
+        }
+        Request::Tree => return Ok(Reply::with_data(&wm.tree().await?)),
+        Request::Subscribe => unreachable!("subscribe requests are handled by the client loop"),
+    }
+    Ok(Reply::ok())
+}
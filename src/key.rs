@@ -1,7 +1,12 @@
+use std::fmt;
+use std::str::FromStr;
+
 use x11rb_async::connection::Connection;
-use x11rb_async::protocol::xproto::{Setup, GetKeyboardMappingReply, ConnectionExt};
+use x11rb_async::protocol::xproto::{Setup, GetKeyboardMappingReply, ConnectionExt, ModMask};
 use xkbcommon::xkb;
 
+use crate::config::Modifier;
+
 /// each x11 server can run different keyboard layout, so converting
 /// key char to keycode is not stright forward, we need to convert the char
 /// to a keysym and convert the keysym to a matching keycode in the retrive
@@ -11,7 +16,7 @@ use xkbcommon::xkb;
 /// is with the given size `keysym_per_keycode` so that how we know how much to advance
 ///
 /// for example, 2d array that look like so
-/// ```
+/// ```text
 /// [
 ///     [1, 2, 3, 4],
 ///     [5, 6, 7, 8]
@@ -19,7 +24,7 @@ use xkbcommon::xkb;
 /// ```
 ///
 /// will be represented as
-/// ```
+/// ```text
 /// keysym_per_keycode = 4; jumps of 4
 ///
 /// [1, 2 ,3 ,4, 5, 6, 7, 8]
@@ -79,40 +84,224 @@ impl KeyState {
     }
 
     /// takes Keysym and returns the equivelent Keycode
-    /// based on the provided keysyms map
+    /// based on the provided keysyms map, only the keycodes
+    /// between the min and max keycodes are searched
     pub fn keysym_to_keycode(&self, keysym: xkb::Keysym) -> Option<xkb::Keycode> {
-        self.keysyms
-            .chunks(self.keysyms_per_keycode as usize)
-            .enumerate()
-            .find_map(|(i, syms)| {
-                syms.contains(&keysym.raw())
-                    .then_some(xkb::Keycode::new(self.min_keycode as u32 + i as u32))
-            })
+        self.keysym_position(keysym).map(|(keycode, _)| keycode)
+    }
+
+    /// returns the keycode of the keysym and the column it was found in, the
+    /// columns are searched in order, so a keysym typed without shift is
+    /// preferred over the same keysym typed with shift on another keycode
+    pub fn keysym_position(&self, keysym: xkb::Keysym) -> Option<(xkb::Keycode, usize)> {
+        let keycodes = self.max_keycode.saturating_sub(self.min_keycode) as usize + 1;
+        let columns = self.keysyms_per_keycode.max(1) as usize;
+        (0..columns).find_map(|column| {
+            self.keysyms
+                .chunks(columns)
+                .take(keycodes)
+                .position(|syms| syms.get(column) == Some(&keysym.raw()))
+                .map(|i| {
+                    let keycode = xkb::Keycode::new(self.min_keycode as u32 + i as u32);
+                    (keycode, column)
+                })
+        })
     }
 }
 
+/// a key as it is written in the config, the key name can be prefixed with
+/// modifiers joined with `+`, those modifiers are required on top of the
+/// global modifier, for example `"Shift+Return"`, `"space"` or `"q"`
 #[derive(Debug, Clone, Eq, PartialEq)]
-#[repr(transparent)]
-pub struct Key(char);
+pub struct Key {
+    keysym: xkb::Keysym,
+    modifiers: ModMask,
+}
 
 impl Key {
     /// returns Keysym representation for the current key
     #[inline]
     pub fn keysym(&self) -> xkb::Keysym {
-        xkb::utf32_to_keysym(self.0 as u32)
+        self.keysym
     }
 
-    /// returns the Keycode for the current char, returns None if
+    /// returns the Keycode for the current key, returns None if
     /// couldn't find Keycode for current key in given KeyState
     #[inline]
     pub fn keycode(&self, state: &KeyState) -> Option<xkb::Keycode> {
-        let keysym = self.keysym();
-        state.keysym_to_keycode(keysym)
+        state.keysym_to_keycode(self.keysym())
+    }
+
+    /// returns the key press that matches the key in the given KeyState, the
+    /// keysyms in the second column of a keycode are typed with shift, so
+    /// `"Q"` is pressed as `"Shift+q"`
+    #[inline]
+    pub fn press(&self, state: &KeyState) -> Option<KeyPress> {
+        state
+            .keysym_position(self.keysym())
+            .map(|(keycode, column)| match column {
+                1 => KeyPress::new(keycode, self.modifiers | ModMask::SHIFT),
+                _ => KeyPress::new(keycode, self.modifiers),
+            })
     }
 }
 
-impl From<char> for Key {
-    fn from(value: char) -> Key {
-        Key(value)
+impl FromStr for Key {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> anyhow::Result<Key> {
+        // the key name is last, so `"Shift++"` is the shift
+        // modifier with the plus key
+        let (modifiers, name) = match value.rsplit_once('+') {
+            Some((modifiers, "")) => (modifiers.strip_suffix('+').unwrap_or(modifiers), "+"),
+            Some((modifiers, name)) => (modifiers, name),
+            None => ("", value),
+        };
+
+        let modifiers = modifiers
+            .split('+')
+            .filter(|modifier| !modifier.is_empty())
+            .try_fold(ModMask::from(0u16), |mask, modifier| {
+                modifier
+                    .parse::<Modifier>()
+                    .map(|modifier| mask | ModMask::from(modifier))
+            })?;
+
+        // named keys like `Return` are looked up first, single chars
+        // that are not key names (like `'`) are converted directly
+        let mut keysym = xkb::keysym_from_name(name, xkb::KEYSYM_NO_FLAGS);
+        if keysym == xkb::Keysym::NoSymbol {
+            let mut chars = name.chars();
+            if let (Some(c), None) = (chars.next(), chars.next()) {
+                keysym = xkb::utf32_to_keysym(c as u32);
+            }
+        }
+
+        if keysym == xkb::Keysym::NoSymbol {
+            anyhow::bail!("unknown key name `{}`", name);
+        }
+        Ok(Key { keysym, modifiers })
+    }
+}
+
+/// a pressed key with the modifiers that were held with it, the
+/// global modifier and the lock modifiers are not part of it
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct KeyPress {
+    keycode: xkb::Keycode,
+    modifiers: ModMask,
+}
+
+impl KeyPress {
+    pub fn new(keycode: xkb::Keycode, modifiers: ModMask) -> Self {
+        Self { keycode, modifiers }
+    }
+
+    #[inline]
+    pub fn keycode(&self) -> xkb::Keycode {
+        self.keycode
+    }
+
+    #[inline]
+    pub fn modifiers(&self) -> ModMask {
+        self.modifiers
+    }
+}
+
+impl fmt::Display for KeyPress {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match u16::from(self.modifiers) {
+            0 => write!(fmt, "{}", self.keycode.raw()),
+            modifiers => write!(fmt, "{:#x}:{}", modifiers, self.keycode.raw()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(keysym: xkb::Keysym, modifiers: ModMask) -> Key {
+        Key { keysym, modifiers }
+    }
+
+    #[test]
+    fn parses_named_keys() {
+        let parsed: Key = "Return".parse().unwrap();
+        assert_eq!(parsed, key(xkb::Keysym::Return, ModMask::from(0u16)));
+
+        let parsed: Key = "space".parse().unwrap();
+        assert_eq!(parsed, key(xkb::Keysym::space, ModMask::from(0u16)));
+    }
+
+    #[test]
+    fn parses_single_chars() {
+        let parsed: Key = "q".parse().unwrap();
+        assert_eq!(parsed, key(xkb::Keysym::q, ModMask::from(0u16)));
+
+        let parsed: Key = "'".parse().unwrap();
+        assert_eq!(parsed, key(xkb::Keysym::apostrophe, ModMask::from(0u16)));
+    }
+
+    #[test]
+    fn parses_modifiers() {
+        let parsed: Key = "Shift+Return".parse().unwrap();
+        assert_eq!(parsed, key(xkb::Keysym::Return, ModMask::SHIFT));
+
+        let parsed: Key = "ctrl+Super+q".parse().unwrap();
+        assert_eq!(parsed, key(xkb::Keysym::q, ModMask::CONTROL | ModMask::M4));
+    }
+
+    #[test]
+    fn parses_the_plus_key() {
+        let parsed: Key = "+".parse().unwrap();
+        assert_eq!(parsed, key(xkb::Keysym::plus, ModMask::from(0u16)));
+
+        let parsed: Key = "Shift++".parse().unwrap();
+        assert_eq!(parsed, key(xkb::Keysym::plus, ModMask::SHIFT));
+    }
+
+    #[test]
+    fn rejects_unknown_names() {
+        assert!("Hyper+q".parse::<Key>().is_err());
+        assert!("NotAKey".parse::<Key>().is_err());
+        assert!("".parse::<Key>().is_err());
+    }
+
+    #[test]
+    fn finds_keycodes_in_range() {
+        let plus = xkb::Keysym::plus.raw();
+        let q = xkb::Keysym::q.raw();
+        let state = KeyState::new(8, 9, 2, vec![q, 0, 0, plus, plus, 0]);
+
+        assert_eq!(
+            state.keysym_to_keycode(xkb::Keysym::q),
+            Some(xkb::Keycode::new(8))
+        );
+        assert_eq!(
+            state.keysym_to_keycode(xkb::Keysym::plus),
+            Some(xkb::Keycode::new(9))
+        );
+        assert_eq!(state.keysym_to_keycode(xkb::Keysym::space), None);
+    }
+
+    #[test]
+    fn shifted_keysyms_are_pressed_with_shift() {
+        let keysyms = [
+            xkb::Keysym::_1,
+            xkb::Keysym::exclam,
+            xkb::Keysym::q,
+            xkb::Keysym::Q,
+        ];
+        let state = KeyState::new(8, 9, 2, keysyms.iter().map(|k| k.raw()).collect());
+        let press = |key: &str| key.parse::<Key>().unwrap().press(&state).unwrap();
+
+        let one = xkb::Keycode::new(8);
+        let q = xkb::Keycode::new(9);
+        assert_eq!(press("1"), KeyPress::new(one, ModMask::from(0u16)));
+        assert_eq!(press("exclam"), KeyPress::new(one, ModMask::SHIFT));
+        assert_eq!(press("Q"), KeyPress::new(q, ModMask::SHIFT));
+        assert_eq!(press("Shift+q"), press("Q"));
+        assert_eq!(press("Shift+Q"), press("Q"));
     }
 }
@@ -0,0 +1,133 @@
+use x11rb_async::protocol::xproto::Window as X11Window;
+
+use super::{Geometry, Layout, LayoutKind};
+use crate::config::LayoutConfig;
+
+/// the classic tiling layout, the first `master_count` windows are placed
+/// in the master column on the left, and the rest are stacked on the right
+/// ```text
+/// +-----------+-------+
+/// |           |   2   |
+/// |     1     +-------+
+/// |           |   3   |
+/// +-----------+-------+
+/// ```
+#[derive(Debug)]
+pub struct MasterStack {
+    master_count: usize,
+    master_ratio: f32,
+}
+
+impl MasterStack {
+    pub fn new(master_count: usize, master_ratio: f32) -> Self {
+        Self {
+            master_count,
+            master_ratio: master_ratio.clamp(0.1, 0.9),
+        }
+    }
+}
+
+impl From<&LayoutConfig> for MasterStack {
+    fn from(config: &LayoutConfig) -> Self {
+        MasterStack::new(config.master_count(), config.master_ratio())
+    }
+}
+
+impl Layout for MasterStack {
+    fn layout_name(&self) -> &str {
+        "master-stack"
+    }
+
+    fn kind(&self) -> LayoutKind {
+        LayoutKind::MasterStack
+    }
+
+    fn arrange(&self, area: Geometry, windows: &[X11Window]) -> Vec<(X11Window, Geometry)> {
+        let masters = self.master_count.min(windows.len());
+
+        // when there are only masters or only stack windows
+        // the single column takes the whole area
+        let geometries = if masters == 0 || masters == windows.len() {
+            area.split_rows(windows.len())
+        } else {
+            let master_width = (area.width as f32 * self.master_ratio) as u32;
+            let master_area = Geometry::new(area.x, area.y, master_width, area.height);
+            let stack_area = Geometry::new(
+                area.x + master_width as i32,
+                area.y,
+                area.width - master_width,
+                area.height,
+            );
+
+            let mut geometries = master_area.split_rows(masters);
+            geometries.extend(stack_area.split_rows(windows.len() - masters));
+            geometries
+        };
+
+        windows.iter().copied().zip(geometries).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AREA: Geometry = Geometry {
+        x: 0,
+        y: 0,
+        width: 1000,
+        height: 600,
+    };
+
+    #[test]
+    fn a_single_window_takes_the_area() {
+        let layout = MasterStack::new(1, 0.5);
+        assert_eq!(layout.arrange(AREA, &[1]), vec![(1, AREA)]);
+        assert!(layout.arrange(AREA, &[]).is_empty());
+    }
+
+    #[test]
+    fn stacks_the_windows_after_the_masters() {
+        let layout = MasterStack::new(1, 0.6);
+        assert_eq!(
+            layout.arrange(AREA, &[1, 2, 3]),
+            vec![
+                (1, Geometry::new(0, 0, 600, 600)),
+                (2, Geometry::new(600, 0, 400, 300)),
+                (3, Geometry::new(600, 300, 400, 300)),
+            ]
+        );
+    }
+
+    #[test]
+    fn only_masters_share_a_single_column() {
+        let layout = MasterStack::new(3, 0.5);
+        assert_eq!(
+            layout.arrange(AREA, &[1, 2]),
+            vec![
+                (1, Geometry::new(0, 0, 1000, 300)),
+                (2, Geometry::new(0, 300, 1000, 300)),
+            ]
+        );
+    }
+
+    #[test]
+    fn no_masters_share_a_single_column() {
+        let layout = MasterStack::new(0, 0.5);
+        assert_eq!(
+            layout.arrange(AREA, &[1, 2]),
+            vec![
+                (1, Geometry::new(0, 0, 1000, 300)),
+                (2, Geometry::new(0, 300, 1000, 300)),
+            ]
+        );
+    }
+
+    #[test]
+    fn clamps_the_master_ratio() {
+        let layout = MasterStack::new(1, 2.0);
+        let arranged = layout.arrange(AREA, &[1, 2]);
+        assert_eq!(arranged[0].1.width, 900);
+        assert_eq!(arranged[1].1, Geometry::new(900, 0, 100, 600));
+    }
+}
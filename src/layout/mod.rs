@@ -0,0 +1,132 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use x11rb_async::protocol::xproto::Window as X11Window;
+
+use crate::config::LayoutConfig;
+
+mod master_stack;
+mod monocle;
+
+pub use master_stack::MasterStack;
+pub use monocle::Monocle;
+
+/// a rectangle on the screen, used both for the area a layout
+/// can use and for the geometries the layout produces
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Geometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Geometry {
+    pub fn new(x: i32, y: i32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// splits the geometry vertically into `count` rows, the last
+    /// row takes the remaining pixels so the rows cover the whole area
+    pub fn split_rows(&self, count: usize) -> Vec<Geometry> {
+        if count == 0 {
+            return Vec::new();
+        }
+
+        let row_height = self.height / count as u32;
+        (0..count)
+            .map(|i| {
+                let y = self.y + (row_height * i as u32) as i32;
+                let height = if i == count - 1 {
+                    self.height - row_height * i as u32
+                } else {
+                    row_height
+                };
+                Geometry::new(self.x, y, self.width, height)
+            })
+            .collect()
+    }
+}
+
+/// the available layouts, used to select a layout from
+/// the config and to cycle between the layouts
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum LayoutKind {
+    #[default]
+    MasterStack,
+    Monocle,
+}
+
+impl LayoutKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            LayoutKind::MasterStack => "master-stack",
+            LayoutKind::Monocle => "monocle",
+        }
+    }
+
+    /// returns the layout that comes after the current one, after the
+    /// last layout it goes back to the first one
+    pub fn next(self) -> LayoutKind {
+        match self {
+            LayoutKind::MasterStack => LayoutKind::Monocle,
+            LayoutKind::Monocle => LayoutKind::MasterStack,
+        }
+    }
+
+    /// creates a new layout of the current kind, configured
+    /// with the given layout config
+    pub fn create(self, config: &LayoutConfig) -> Box<dyn Layout> {
+        match self {
+            LayoutKind::MasterStack => Box::new(MasterStack::from(config)),
+            LayoutKind::Monocle => Box::new(Monocle),
+        }
+    }
+}
+
+impl fmt::Display for LayoutKind {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "{}", self.name())
+    }
+}
+
+/// a trait that is implemented on types that can arrange
+/// the windows of a workspace on the screen
+pub trait Layout: Send + Sync {
+    fn layout_name(&self) -> &str;
+
+    fn kind(&self) -> LayoutKind;
+
+    /// takes the area the layout can use and the workspace windows in their
+    /// order, returns the geometry each window should get
+    fn arrange(&self, area: Geometry, windows: &[X11Window]) -> Vec<(X11Window, Geometry)>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_rows_covers_the_whole_area() {
+        let rows = Geometry::new(10, 20, 100, 100).split_rows(3);
+        assert_eq!(
+            rows,
+            vec![
+                Geometry::new(10, 20, 100, 33),
+                Geometry::new(10, 53, 100, 33),
+                Geometry::new(10, 86, 100, 34),
+            ]
+        );
+    }
+
+    #[test]
+    fn split_rows_into_nothing() {
+        assert!(Geometry::new(0, 0, 100, 100).split_rows(0).is_empty());
+    }
+}
@@ -0,0 +1,22 @@
+use x11rb_async::protocol::xproto::Window as X11Window;
+
+use super::{Geometry, Layout, LayoutKind};
+
+/// every window takes the whole area, only the
+/// window on top of the stack is visible
+#[derive(Debug, Default)]
+pub struct Monocle;
+
+impl Layout for Monocle {
+    fn layout_name(&self) -> &str {
+        "monocle"
+    }
+
+    fn kind(&self) -> LayoutKind {
+        LayoutKind::Monocle
+    }
+
+    fn arrange(&self, area: Geometry, windows: &[X11Window]) -> Vec<(X11Window, Geometry)> {
+        windows.iter().map(|&window| (window, area)).collect()
+    }
+}
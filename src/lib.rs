@@ -3,46 +3,76 @@ use std::path::PathBuf;
 
 use anyhow::Context;
 
+mod action;
+mod atoms;
+mod check;
 mod workspace;
 mod combos;
 mod config;
+mod ewmh;
 mod logger;
+mod property;
+mod selection;
 mod window;
 mod key;
+mod layout;
+mod watch;
 mod wm;
+mod xdg;
 
-pub use config::Config;
-pub use logger::Logger;
+pub mod ipc;
+
+pub use action::Action;
+pub use check::check_config;
+pub use config::{Config, DEFAULT_CONFIG, SpawnCommand};
+pub use logger::{LogFilter, LogFormat, LogOptions, Logger};
+pub use layout::LayoutKind;
+pub use watch::watch_config;
 pub use wm::WindowManager;
 
 /// tries to find the given config filename at predefined location
 /// the priority and locations
-///     `./flow.cfg`
-///     `$HOME/flow.cfg`
-///     `$HOEM/.config/flow.cfg`
+///     `./flow.toml`
+///     `$XDG_CONFIG_HOME/flow/flow.toml`, `$HOME/.config/flow/flow.toml` if unset
+///     `$HOME/flow.toml`
+///     `$HOME/.config/flow.toml`
+///     `$XDG_CONFIG_DIRS/flow/flow.toml`, `/etc/xdg/flow/flow.toml` if unset
 pub fn find_config_path(filename: &str) -> anyhow::Result<PathBuf> {
     // if the config file is in the current directory
     // it has the highest priority so we need to return it
-    let current = PathBuf::from(filename);
-    if current.exists() {
-        return Ok(current);
-    }
-
-    // find the user home directory based
-    // on the `HOME` environment variable
-    let home: PathBuf = env::var("HOME")
-        .context("couldn't resolve `HOME` environment variable")?
-        .into();
+    let mut candidates = vec![PathBuf::from(filename)];
 
-    let path = home.join(filename);
-    if path.exists() {
-        return Ok(path);
+    if let Some(config_home) = xdg::config_home() {
+        candidates.push(config_home.join("flow").join(filename));
     }
 
-    let path = home.join(".config").join(filename);
-    if path.exists() {
-        return Ok(path);
+    // the locations flow used before following the XDG
+    // specification are still checked for older setups
+    if let Ok(home) = env::var("HOME") {
+        let home = PathBuf::from(home);
+        candidates.push(home.join(filename));
+        candidates.push(home.join(".config").join(filename));
     }
 
-    Err(anyhow::anyhow!("couldn't find config file anywhere"))
+    candidates.extend(
+        xdg::config_dirs()
+            .into_iter()
+            .map(|dir| dir.join("flow").join(filename)),
+    );
+
+    candidates
+        .into_iter()
+        .find(|path| path.exists())
+        .ok_or_else(|| anyhow::anyhow!("couldn't find config file anywhere"))
+}
+
+/// the log file path when none is given, `$XDG_STATE_HOME/flow/flow.log`,
+/// the directory is created if it doesn't exist
+pub fn default_log_path() -> anyhow::Result<PathBuf> {
+    let directory = xdg::state_home()
+        .context("couldn't resolve `XDG_STATE_HOME` or `HOME` environment variables")?
+        .join("flow");
+    std::fs::create_dir_all(&directory)
+        .with_context(|| format!("couldn't create log directory `{}`", directory.display()))?;
+    Ok(directory.join("flow.log"))
 }
@@ -1,54 +1,404 @@
+use std::cmp::Reverse;
+use std::fs::File;
 use std::io::{self, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::sync::mpsc;
+use std::time::SystemTime;
 
-use log::{Metadata, Record};
+use log::{LevelFilter, Metadata, Record};
+use serde::Deserialize;
+
+/// how each log line is written
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum LogFormat {
+    /// `<timestamp> <level> <module>: <message>`
+    #[default]
+    Text,
+
+    /// a JSON object per line with the `timestamp`, `level`,
+    /// `module` and `message` fields
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> anyhow::Result<LogFormat> {
+        match value {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(anyhow::anyhow!("unknown log format `{}`", value)),
+        }
+    }
+}
+
+/// the levels written to the log, written as a default level followed by
+/// per module levels, the most specific module wins
+///
+/// ```text
+/// info,flow::wm=debug,flow::ipc=trace
+/// ```
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[serde(try_from = "String")]
+pub struct LogFilter {
+    default: LevelFilter,
+
+    /// the module paths with their levels, sorted from
+    /// the longest path so the first match is the most specific
+    modules: Vec<(String, LevelFilter)>,
+}
+
+impl LogFilter {
+    pub fn new(default: LevelFilter) -> Self {
+        Self {
+            default,
+            modules: Vec::new(),
+        }
+    }
+
+    /// the level that applies to the given module path
+    pub fn level(&self, module: &str) -> LevelFilter {
+        self.modules
+            .iter()
+            .find(|(path, _)| {
+                module
+                    .strip_prefix(path.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+            })
+            .map_or(self.default, |&(_, level)| level)
+    }
+
+    /// the most verbose level any module can log with
+    pub fn max_level(&self) -> LevelFilter {
+        self.modules
+            .iter()
+            .map(|&(_, level)| level)
+            .fold(self.default, Ord::max)
+    }
+}
+
+impl Default for LogFilter {
+    fn default() -> Self {
+        LogFilter::new(LevelFilter::Debug)
+    }
+}
+
+impl FromStr for LogFilter {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> anyhow::Result<LogFilter> {
+        let mut filter = LogFilter::default();
+        for directive in value.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            let parse = |level: &str| {
+                level
+                    .trim()
+                    .parse::<LevelFilter>()
+                    .map_err(|_| anyhow::anyhow!("unknown log level `{}`", level.trim()))
+            };
+
+            match directive.split_once('=') {
+                Some((module, level)) => filter
+                    .modules
+                    .push((module.trim().to_string(), parse(level)?)),
+                None => filter.default = parse(directive)?,
+            }
+        }
+
+        filter
+            .modules
+            .sort_by_key(|(module, _)| Reverse(module.len()));
+        Ok(filter)
+    }
+}
+
+impl TryFrom<String> for LogFilter {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> anyhow::Result<LogFilter> {
+        value.parse()
+    }
+}
+
+/// the options the `Logger` is created with
+#[derive(Debug, Clone)]
+pub struct LogOptions {
+    pub filter: LogFilter,
+    pub format: LogFormat,
+
+    /// also print every log line to stdout
+    pub echo: bool,
+
+    /// the size in bytes a log file can reach before it is rotated
+    pub max_size: u64,
+
+    /// how many rotated log files are kept next to the current one
+    pub keep: usize,
+}
+
+impl Default for LogOptions {
+    fn default() -> Self {
+        Self {
+            filter: LogFilter::default(),
+            format: LogFormat::default(),
+            echo: false,
+            max_size: 10 * 1024 * 1024,
+            keep: 5,
+        }
+    }
+}
+
+/// a log file that is rotated once it grows past its maximum size, the
+/// rotated files are named after the log file with an increasing number,
+/// `flow.log.1` being the most recent one
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_size: u64,
+    keep: usize,
+}
+
+impl RotatingFile {
+    /// opens a new log file at the given path, an existing log file
+    /// is rotated first so the previous session's log is kept
+    fn open(path: &Path, max_size: u64, keep: usize) -> io::Result<Self> {
+        // at least one rotated file is always kept, otherwise
+        // rotating would be the same as truncating
+        let keep = keep.max(1);
+        if path.metadata().is_ok_and(|metadata| metadata.len() > 0) {
+            rotate(path, keep)?;
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: create(path)?,
+            size: 0,
+            max_size,
+            keep,
+        })
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_size {
+            self.file.flush()?;
+            rotate(&self.path, self.keep)?;
+            self.file = create(&self.path)?;
+            self.size = 0;
+        }
+
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+fn create(path: &Path) -> io::Result<File> {
+    std::fs::OpenOptions::new()
+        .create(true)
+        .truncate(true)
+        .write(true)
+        .open(path)
+}
+
+/// shifts each rotated file one number up, dropping the oldest,
+/// and moves the current log file to `<path>.1`
+fn rotate(path: &Path, keep: usize) -> io::Result<()> {
+    let numbered = |number: usize| {
+        let mut name = path.as_os_str().to_os_string();
+        name.push(format!(".{}", number));
+        PathBuf::from(name)
+    };
+
+    for number in (1..keep).rev() {
+        let from = numbered(number);
+        if from.exists() {
+            std::fs::rename(from, numbered(number + 1))?;
+        }
+    }
+    std::fs::rename(path, numbered(1))
+}
 
 /// the standard logger type that is used, at creation it will spawns
 /// a new thread that will handle writes to the given writer, so the logging
 /// operations will never block
 pub struct Logger {
     tx: mpsc::Sender<String>,
+    filter: LogFilter,
+    format: LogFormat,
 }
 
 impl Logger {
-    pub fn new<W: Write + Sync + Send + 'static>(stream: W) -> Self {
+    pub fn new<W: Write + Sync + Send + 'static>(stream: W, options: LogOptions) -> Self {
         let (tx, rx) = mpsc::channel::<String>();
 
+        let echo = options.echo;
         std::thread::spawn(move || {
             let mut stream = stream;
             while let Ok(message) = rx.recv() {
-                print!("{}", message);
+                if echo {
+                    print!("{}", message);
+                }
                 let _ = stream.write_all(message.as_bytes());
             }
         });
 
-        Logger { tx }
+        Logger {
+            tx,
+            filter: options.filter,
+            format: options.format,
+        }
     }
 
-    /// creates a new logger that writes to the given filepath
-    pub fn from_path<P>(path: P) -> io::Result<Self>
+    /// creates a new logger that writes to the given filepath, the log
+    /// file is rotated on start and whenever it grows past `max_size`
+    pub fn from_path<P>(path: P, options: LogOptions) -> io::Result<Self>
     where
         P: AsRef<Path>,
     {
-        let file = std::fs::OpenOptions::new()
-            .create(true)
-            .truncate(true)
-            .write(true)
-            .open(path)?;
-        Ok(Logger::new(file))
+        let file = RotatingFile::open(path.as_ref(), options.max_size, options.keep)?;
+        Ok(Logger::new(file, options))
+    }
+
+    fn format(&self, record: &Record) -> String {
+        let timestamp = humantime::format_rfc3339_millis(SystemTime::now());
+        let module = record.module_path().unwrap_or(record.target());
+        match self.format {
+            LogFormat::Text => format!(
+                "{} {:<5} {}: {}\n",
+                timestamp,
+                record.level(),
+                module,
+                record.args()
+            ),
+            LogFormat::Json => {
+                let line = serde_json::json!({
+                    "timestamp": timestamp.to_string(),
+                    "level": record.level().as_str(),
+                    "module": module,
+                    "message": record.args().to_string(),
+                });
+                format!("{}\n", line)
+            }
+        }
     }
 }
 
 impl log::Log for Logger {
     fn enabled(&self, metadata: &Metadata) -> bool {
-        metadata.level() <= log::max_level()
+        metadata.level() <= self.filter.level(metadata.target())
     }
 
     fn log(&self, record: &Record) {
-        let message = format!("[{}] {}\n", record.metadata().level(), record.args());
-        let _ = self.tx.send(message);
+        if self.enabled(record.metadata()) {
+            let _ = self.tx.send(self.format(record));
+        }
     }
 
     fn flush(&self) {}
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_a_default_level() {
+        let filter: LogFilter = "warn".parse().unwrap();
+        assert_eq!(filter, LogFilter::new(LevelFilter::Warn));
+        assert_eq!(filter.level("flow::wm"), LevelFilter::Warn);
+    }
+
+    #[test]
+    fn empty_filters_keep_the_default() {
+        let filter: LogFilter = "".parse().unwrap();
+        assert_eq!(filter, LogFilter::default());
+    }
+
+    #[test]
+    fn the_most_specific_module_wins() {
+        let filter: LogFilter = "info, flow=warn, flow::wm=trace".parse().unwrap();
+        assert_eq!(filter.level("flow::wm"), LevelFilter::Trace);
+        assert_eq!(filter.level("flow::wm::focus"), LevelFilter::Trace);
+        assert_eq!(filter.level("flow::ipc"), LevelFilter::Warn);
+        assert_eq!(filter.level("x11rb"), LevelFilter::Info);
+        assert_eq!(filter.max_level(), LevelFilter::Trace);
+    }
+
+    #[test]
+    fn modules_match_whole_path_segments() {
+        let filter: LogFilter = "info,flow::wm=debug".parse().unwrap();
+        assert_eq!(filter.level("flow::wmctl"), LevelFilter::Info);
+    }
+
+    /// a fresh directory for a test, removed when the test is done
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path =
+                std::env::temp_dir().join(format!("flow-logger-{}-{}", std::process::id(), name));
+            let _ = std::fs::remove_dir_all(&path);
+            std::fs::create_dir_all(&path).unwrap();
+            TempDir(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn read(path: PathBuf) -> String {
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn keeps_the_previous_session_log() {
+        let dir = TempDir::new("sessions");
+        let path = dir.0.join("flow.log");
+
+        let mut file = RotatingFile::open(&path, 1024, 5).unwrap();
+        file.write_all(b"first session").unwrap();
+        drop(file);
+
+        let mut file = RotatingFile::open(&path, 1024, 5).unwrap();
+        file.write_all(b"second session").unwrap();
+        drop(file);
+
+        assert_eq!(read(dir.0.join("flow.log")), "second session");
+        assert_eq!(read(dir.0.join("flow.log.1")), "first session");
+        assert!(!dir.0.join("flow.log.2").exists());
+    }
+
+    #[test]
+    fn keeps_only_the_configured_files() {
+        let dir = TempDir::new("rotation");
+        let path = dir.0.join("flow.log");
+
+        let mut file = RotatingFile::open(&path, 8, 2).unwrap();
+        for line in ["line 1\n", "line 2\n", "line 3\n", "line 4\n"] {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+        file.flush().unwrap();
+
+        assert_eq!(read(dir.0.join("flow.log")), "line 4\n");
+        assert_eq!(read(dir.0.join("flow.log.1")), "line 3\n");
+        assert_eq!(read(dir.0.join("flow.log.2")), "line 2\n");
+        assert!(!dir.0.join("flow.log.3").exists());
+    }
+
+    #[test]
+    fn rejects_unknown_levels() {
+        assert!("loud".parse::<LogFilter>().is_err());
+        assert!("info,flow::wm=loud".parse::<LogFilter>().is_err());
+    }
+}
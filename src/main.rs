@@ -1,23 +1,109 @@
-use std::path::Path;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+use clap::Parser;
 use x11rb_async::connection::Connection;
 use x11rb_async::rust_connection::RustConnection;
 
+#[derive(Debug, Parser)]
+#[command(name = "flow", version, about = "a tiling window manager for x11")]
+struct Cli {
+    /// the x11 display to manage, defaults to the `DISPLAY` environment variable
+    #[arg(long, value_name = "DISPLAY")]
+    display: Option<String>,
+
+    /// check the config file for problems and exit, the config file flow
+    /// would load is checked if no path is given, or the built-in config
+    /// if there is no config file
+    #[arg(long, value_name = "PATH")]
+    check_config: Option<Option<PathBuf>>,
+
+    /// replace the running window manager instead of failing to start
+    #[arg(long)]
+    replace: bool,
+
+    /// the config file to use instead of searching for one
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// the log file, defaults to `$XDG_STATE_HOME/flow/flow.log`
+    #[arg(long, value_name = "PATH")]
+    log_file: Option<PathBuf>,
+
+    /// the log levels, a level of off, error, warn, info, debug or trace
+    /// optionally followed by per module levels, like `info,flow::wm=debug`,
+    /// overrides the `FLOW_LOG` environment variable and the config
+    #[arg(long, value_name = "LEVEL")]
+    log_level: Option<flow::LogFilter>,
+
+    /// the log lines format, `text` or `json`
+    #[arg(long, value_name = "FORMAT")]
+    log_format: Option<flow::LogFormat>,
+
+    /// print the log lines to stdout on top of the log file
+    #[arg(long)]
+    log_stdout: bool,
+
+    /// write the built-in default config to the given path, or to
+    /// stdout if no path is given, and exit
+    #[arg(long, value_name = "PATH")]
+    dump_default_config: Option<Option<PathBuf>>,
+}
+
 #[inline]
-fn setup_logger<P>(path: P) -> anyhow::Result<()>
+fn setup_logger<P>(path: P, options: flow::LogOptions) -> anyhow::Result<()>
 where
     P: AsRef<Path>,
 {
-    let logger = flow::Logger::from_path(path)?;
+    let level = options.filter.max_level();
+    let logger = flow::Logger::from_path(path, options)?;
     // safe to unwrap, a logger should not have been
     // set at this point
     log::set_boxed_logger(Box::new(logger))
-        .map(|()| log::set_max_level(log::LevelFilter::Debug))
+        .map(|()| log::set_max_level(level))
         .unwrap();
     Ok(())
 }
 
+/// the log options from the command line, the `FLOW_LOG`
+/// environment variable and the config, in that priority
+fn log_options(cli: &Cli, config: &flow::Config) -> anyhow::Result<flow::LogOptions> {
+    let filter = match (&cli.log_level, std::env::var("FLOW_LOG")) {
+        (Some(filter), _) => filter.clone(),
+        (None, Ok(filter)) => filter
+            .parse()
+            .map_err(|err| anyhow::anyhow!("invalid `FLOW_LOG` environment variable, {}", err))?,
+        (None, Err(_)) => config.flow().log_level().cloned().unwrap_or_default(),
+    };
+
+    Ok(flow::LogOptions {
+        filter,
+        format: cli.log_format.unwrap_or(config.flow().log_format()),
+        echo: cli.log_stdout || config.flow().log_stdout(),
+        max_size: config.flow().log_max_size(),
+        keep: config.flow().log_keep(),
+    })
+}
+
+/// loads the given config file, or the one found in the config locations,
+/// without a config file flow still starts with the built-in config, a config
+/// file that fails to load is returned as an error next to the built-in
+/// config, so a typo doesn't keep flow from starting
+fn load_config(path: Option<PathBuf>) -> (flow::Config, Option<PathBuf>, Option<anyhow::Error>) {
+    let found = match path {
+        Some(path) => Ok(path),
+        None => flow::find_config_path("flow.toml"),
+    };
+    match found {
+        Ok(path) => match flow::Config::from_path(&path) {
+            Ok(config) => (config, Some(path), None),
+            Err(err) => (flow::Config::builtin(), Some(path), Some(err)),
+        },
+        Err(_) => (flow::Config::builtin(), None, None),
+    }
+}
+
 /// bofore panicing and existing from the window-manager
 /// we log the critical error
 #[inline]
@@ -27,15 +113,21 @@ fn setup_hooks() {
     }));
 }
 
-async fn run() -> anyhow::Result<()> {
-    let config = flow::find_config_path("flow.toml").and_then(|cfg_path| {
-        log::debug!("found config file at `{}`", cfg_path.display());
-        flow::Config::from_path(cfg_path)
-    })?;
+async fn run(
+    display: Option<&str>,
+    replace: bool,
+    config: flow::Config,
+    config_path: Option<PathBuf>,
+) -> anyhow::Result<()> {
+    match &config_path {
+        Some(path) => log::debug!("found config file at `{}`", path.display()),
+        None => log::info!("no config file found, using the built-in default config"),
+    }
+    let watch_config = config.flow().watch_config();
 
     log::info!("starting rust connection to x11 server");
-    let (connection, display, derive) = RustConnection::connect(None).await?;
-    let root = connection.setup().roots[display].root;
+    let (connection, screen, derive) = RustConnection::connect(display).await?;
+    let root = connection.setup().roots[screen].root;
 
     // create a background task to put the derive
     // on the execution loop task
@@ -46,18 +138,145 @@ async fn run() -> anyhow::Result<()> {
         }
     });
 
-    let wm = Arc::new(flow::WindowManager::with_connection(connection, root, config).await?);
+    let wm = Arc::new(
+        flow::WindowManager::with_connection(
+            connection,
+            root,
+            config,
+            config_path.clone(),
+            replace,
+        )
+        .await?,
+    );
+
+    // the ipc server is optional, the window manager can
+    // work without it if the socket couldn't be created
+    match flow::ipc::default_socket_path().and_then(flow::ipc::IpcServer::bind) {
+        Ok(server) => {
+            let wm = Arc::clone(&wm);
+            tokio::spawn(async move {
+                if let Err(err) = server.serve(wm).await {
+                    log::error!("ipc server stopped, {}", err);
+                }
+            });
+        }
+        Err(err) => log::error!("couldn't start the ipc server, {}", err),
+    }
+
+    if let Some(config_path) = config_path.filter(|_| watch_config) {
+        let wm = Arc::clone(&wm);
+        tokio::spawn(async move {
+            if let Err(err) = flow::watch_config(config_path, wm).await {
+                log::error!("stopped watching the config file, {}", err);
+            }
+        });
+    }
+
     wm.run().await
 }
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    setup_logger("flow.log")?;
+/// prints every problem found in the config file, the built-in config is
+/// checked when there is no config file, the keys are checked against the
+/// keyboard layout when the x11 server is reachable
+async fn check_config(path: Option<PathBuf>, display: Option<&str>) -> anyhow::Result<()> {
+    let path = path.or_else(|| flow::find_config_path("flow.toml").ok());
+    let name = match &path {
+        Some(path) => format!("`{}`", path.display()),
+        None => "the built-in config".to_string(),
+    };
+    if path.is_none() {
+        println!("couldn't find a config file, checking the built-in config");
+    }
+
+    let connection = match RustConnection::connect(display).await {
+        Ok((connection, _, derive)) => {
+            tokio::spawn(derive);
+            Some(connection)
+        }
+        Err(err) => {
+            eprintln!(
+                "couldn't connect to the x11 server, skipping the keyboard layout checks, {}",
+                err
+            );
+            None
+        }
+    };
+
+    let problems = flow::check_config(path.as_deref(), connection.as_ref()).await?;
+    for problem in &problems {
+        eprintln!("{}: {}", name, problem);
+    }
+    if !problems.is_empty() {
+        anyhow::bail!("found {} problems in {}", problems.len(), name);
+    }
+
+    println!("{} is valid", name);
+    Ok(())
+}
+
+/// writes the built-in config, an existing file is never overwritten
+fn dump_default_config(path: Option<PathBuf>) -> anyhow::Result<()> {
+    let Some(path) = path else {
+        print!("{}", flow::DEFAULT_CONFIG);
+        return Ok(());
+    };
+
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .map_err(|err| anyhow::anyhow!("couldn't create `{}`, {}", path.display(), err))?;
+    file.write_all(flow::DEFAULT_CONFIG.as_bytes())?;
+    println!("wrote the default config to `{}`", path.display());
+    Ok(())
+}
+
+fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    if let Some(display) = &cli.display {
+        // SAFETY: the runtime isn't built yet, so no other thread can read
+        // the environment, the spawned processes and the ipc socket
+        // path follow the chosen display through `DISPLAY`
+        unsafe { std::env::set_var("DISPLAY", display) };
+    }
+
+    tokio::runtime::Runtime::new()?.block_on(start(cli))
+}
+
+async fn start(cli: Cli) -> anyhow::Result<()> {
+    if let Some(path) = cli.dump_default_config {
+        if let Err(err) = dump_default_config(path) {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if let Some(path) = cli.check_config {
+        if let Err(err) = check_config(path.or(cli.config), cli.display.as_deref()).await {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // the config is loaded before the logger, since it configures the logger
+    let (config, config_path, config_error) = load_config(cli.config.clone());
+    let log_file = match &cli.log_file {
+        Some(path) => path.clone(),
+        None => flow::default_log_path()?,
+    };
+    setup_logger(log_file, log_options(&cli, &config)?)?;
     setup_hooks();
+    if let Some(err) = config_error {
+        log::error!("{}, using the built-in config", err);
+    }
     // after successfull logger and panic hooks setups
     // we call the run function which holds the real functionality
     // and we log the errors returned if any
-    run().await.inspect_err(|err| {
-        log::error!("exit with error, {}", err);
-    })
+    run(cli.display.as_deref(), cli.replace, config, config_path)
+        .await
+        .inspect_err(|err| {
+            log::error!("exit with error, {}", err);
+        })
 }
@@ -0,0 +1,100 @@
+/// helpers for reading and writing window properties, the async connection
+/// only provides the raw property requests that work with bytes
+use x11rb_async::connection::Connection;
+use x11rb_async::protocol::xproto::{Atom, ConnectionExt as _, PropMode, Window};
+
+/// replaces the property with the given list of 32 bit values
+pub async fn set_u32<C>(
+    connection: &C,
+    window: Window,
+    property: Atom,
+    type_: Atom,
+    data: &[u32],
+) -> anyhow::Result<()>
+where
+    C: Connection,
+{
+    let bytes: Vec<u8> = data.iter().flat_map(|value| value.to_ne_bytes()).collect();
+    connection
+        .change_property(
+            PropMode::REPLACE,
+            window,
+            property,
+            type_,
+            32,
+            data.len() as u32,
+            &bytes,
+        )
+        .await?;
+    Ok(())
+}
+
+/// reads the property as a list of 32 bit values, returns an empty
+/// list if the property doesn't exist or has a different format
+pub async fn get_u32<C>(
+    connection: &C,
+    window: Window,
+    property: Atom,
+    type_: Atom,
+) -> anyhow::Result<Vec<u32>>
+where
+    C: Connection,
+{
+    let reply = connection
+        .get_property(false, window, property, type_, 0, u32::MAX)
+        .await?
+        .reply()
+        .await?;
+    Ok(reply
+        .value32()
+        .map(|values| values.collect())
+        .unwrap_or_default())
+}
+
+/// reads the property as a string, returns `None` if the property doesn't
+/// exist, invalid utf8 sequences are replaced instead of failing
+pub async fn get_string<C>(
+    connection: &C,
+    window: Window,
+    property: Atom,
+    type_: Atom,
+) -> anyhow::Result<Option<String>>
+where
+    C: Connection,
+{
+    let reply = connection
+        .get_property(false, window, property, type_, 0, u32::MAX)
+        .await?
+        .reply()
+        .await?;
+    if reply.format != 8 {
+        return Ok(None);
+    }
+    Ok(Some(String::from_utf8_lossy(&reply.value).into_owned()))
+}
+
+/// replaces the property with the given string, the string is
+/// written as is, without a terminating null byte
+pub async fn set_string<C>(
+    connection: &C,
+    window: Window,
+    property: Atom,
+    type_: Atom,
+    value: &str,
+) -> anyhow::Result<()>
+where
+    C: Connection,
+{
+    connection
+        .change_property(
+            PropMode::REPLACE,
+            window,
+            property,
+            type_,
+            8,
+            value.len() as u32,
+            value.as_bytes(),
+        )
+        .await?;
+    Ok(())
+}
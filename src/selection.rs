@@ -0,0 +1,198 @@
+/// the ICCCM window manager selection, the window manager of a screen owns
+/// the `WM_S<screen>` selection, so another window manager can ask it to
+/// step down by taking the selection over
+use std::time::Duration;
+
+use x11rb_async::connection::Connection;
+use x11rb_async::protocol::Event;
+use x11rb_async::protocol::xproto::{
+    Atom, AtomEnum, ChangeWindowAttributesAux, ClientMessageEvent, ConnectionExt as _,
+    CreateWindowAux, EventMask, PropMode, SelectionClearEvent, Timestamp, Window, WindowClass,
+    WindowEnum,
+};
+
+use crate::atoms::Atoms;
+
+/// how long the previous window manager has to exit after
+/// it lost the selection, before flow takes over anyway
+const REPLACE_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct ManagerSelection {
+    /// the selection owner window, it is never mapped
+    window: Window,
+    selection: Atom,
+}
+
+impl ManagerSelection {
+    /// takes the `WM_S<screen>` selection, if another window manager owns it
+    /// flow fails unless `replace` is set, then flow takes the selection and
+    /// waits for the previous window manager to exit
+    pub async fn acquire<C>(
+        connection: &C,
+        root: Window,
+        screen: usize,
+        atoms: &Atoms,
+        replace: bool,
+    ) -> anyhow::Result<Self>
+    where
+        C: Connection,
+    {
+        let name = format!("WM_S{}", screen);
+        let selection = connection
+            .intern_atom(false, name.as_bytes())
+            .await?
+            .reply()
+            .await?
+            .atom;
+
+        let owner = connection
+            .get_selection_owner(selection)
+            .await?
+            .reply()
+            .await?
+            .owner;
+        let previous = (owner != u32::from(WindowEnum::NONE)).then_some(owner);
+
+        if let Some(previous) = previous {
+            if !replace {
+                anyhow::bail!(
+                    "another window manager is running, start flow with `--replace` to replace it"
+                );
+            }
+
+            // the previous window manager destroys its selection window when it exits
+            log::info!("replacing the running window manager");
+            connection
+                .change_window_attributes(
+                    previous,
+                    &ChangeWindowAttributesAux::new().event_mask(EventMask::STRUCTURE_NOTIFY),
+                )
+                .await?;
+        }
+
+        let window = connection.generate_id().await?;
+        connection
+            .create_window(
+                0,
+                window,
+                root,
+                -1,
+                -1,
+                1,
+                1,
+                0,
+                WindowClass::INPUT_ONLY,
+                0,
+                &CreateWindowAux::new()
+                    .override_redirect(1)
+                    .event_mask(EventMask::PROPERTY_CHANGE),
+            )
+            .await?
+            .check()
+            .await?;
+
+        let time = server_time(connection, window).await?;
+        connection
+            .set_selection_owner(window, selection, time)
+            .await?;
+        let owner = connection
+            .get_selection_owner(selection)
+            .await?
+            .reply()
+            .await?
+            .owner;
+        if owner != window {
+            anyhow::bail!("couldn't acquire the `{}` selection", name);
+        }
+
+        if let Some(previous) = previous {
+            wait_for_destroy(connection, previous).await?;
+        }
+
+        // announce the new manager to the clients that wait for one
+        let event =
+            ClientMessageEvent::new(32, root, atoms.MANAGER, [time, selection, window, 0, 0]);
+        connection
+            .send_event(false, root, EventMask::STRUCTURE_NOTIFY, event)
+            .await?;
+
+        Ok(Self { window, selection })
+    }
+
+    /// true if the event means another window manager took the selection
+    #[inline]
+    pub fn is_lost(&self, event: &SelectionClearEvent) -> bool {
+        event.owner == self.window && event.selection == self.selection
+    }
+
+    /// stops redirecting the root window events and destroys the selection
+    /// window, which tells the window manager that replaced flow it can
+    /// start managing the screen
+    pub async fn release<C>(&self, connection: &C, root: Window) -> anyhow::Result<()>
+    where
+        C: Connection,
+    {
+        connection
+            .change_window_attributes(
+                root,
+                &ChangeWindowAttributesAux::new().event_mask(EventMask::NO_EVENT),
+            )
+            .await?;
+        connection
+            .destroy_window(self.window)
+            .await?
+            .check()
+            .await?;
+        Ok(())
+    }
+}
+
+/// gets a server timestamp for the selection request, the ICCCM doesn't allow
+/// `CurrentTime`, a zero length property change is reported with the time
+async fn server_time<C>(connection: &C, window: Window) -> anyhow::Result<Timestamp>
+where
+    C: Connection,
+{
+    connection
+        .change_property(
+            PropMode::APPEND,
+            window,
+            AtomEnum::WM_NAME,
+            AtomEnum::STRING,
+            8,
+            0,
+            &[],
+        )
+        .await?;
+
+    loop {
+        if let Event::PropertyNotify(event) = connection.wait_for_event().await?
+            && event.window == window
+        {
+            return Ok(event.time);
+        }
+    }
+}
+
+async fn wait_for_destroy<C>(connection: &C, window: Window) -> anyhow::Result<()>
+where
+    C: Connection,
+{
+    let destroyed = async {
+        loop {
+            if let Event::DestroyNotify(event) = connection.wait_for_event().await?
+                && event.window == window
+            {
+                return anyhow::Ok(());
+            }
+        }
+    };
+
+    match tokio::time::timeout(REPLACE_TIMEOUT, destroyed).await {
+        Ok(result) => result,
+        Err(_) => {
+            log::warn!("the previous window manager didn't exit in time, taking over anyway");
+            Ok(())
+        }
+    }
+}
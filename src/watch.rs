@@ -0,0 +1,79 @@
+/// watches the config file for changes, so edits are applied without
+/// reloading by hand, the parent directory is watched since most
+/// editors save by replacing the file instead of writing into it
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use inotify::{Inotify, WatchMask};
+use tokio::sync::mpsc;
+use x11rb_async::connection::Connection;
+
+use crate::action::Action;
+use crate::wm::WindowManager;
+
+/// editors often write the file in several steps, the changes
+/// that happen within this delay trigger a single reload
+const SETTLE_DELAY: Duration = Duration::from_millis(200);
+
+/// reloads the window manager config every time the file at the given
+/// path changes, returns once the watch can no longer be read
+pub async fn watch_config<C>(path: PathBuf, wm: Arc<WindowManager<C>>) -> anyhow::Result<()>
+where
+    C: Connection + Sync + Send + 'static,
+{
+    let (directory, filename) = split_path(&path)?;
+    let mut inotify = Inotify::init()?;
+    inotify.watches().add(
+        &directory,
+        WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO | WatchMask::CREATE,
+    )?;
+    log::info!("watching config file `{}` for changes", path.display());
+
+    // inotify only offers a blocking read without its stream feature,
+    // the events are read on a dedicated thread and handed over
+    let (sender, mut receiver) = mpsc::channel(8);
+    std::thread::spawn(move || {
+        let mut buffer = [0; 4096];
+        loop {
+            let events = match inotify.read_events_blocking(&mut buffer) {
+                Ok(events) => events,
+                Err(err) => {
+                    log::error!("couldn't read config file changes, {}", err);
+                    return;
+                }
+            };
+
+            let changed = events
+                .into_iter()
+                .any(|event| event.name == Some(&*filename));
+            if changed && sender.blocking_send(()).is_err() {
+                return;
+            }
+        }
+    });
+
+    while receiver.recv().await.is_some() {
+        tokio::time::sleep(SETTLE_DELAY).await;
+        while receiver.try_recv().is_ok() {}
+
+        log::info!("config file changed, reloading it");
+        if let Err(err) = wm.execute(&Action::Reload).await {
+            log::error!("couldn't reload the changed config, {}", err);
+        }
+    }
+    Ok(())
+}
+
+fn split_path(path: &Path) -> anyhow::Result<(PathBuf, OsString)> {
+    let filename = path
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("`{}` is not a file path", path.display()))?
+        .to_os_string();
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    Ok((directory, filename))
+}
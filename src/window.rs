@@ -1,23 +1,599 @@
 use std::sync::Arc;
+use std::time::Duration;
 use x11rb_async::connection::Connection;
-use x11rb_async::protocol::xproto::Window as X11Window;
+use x11rb_async::protocol::xproto::{
+    Atom, AtomEnum, ButtonIndex, ChangeWindowAttributesAux, ClientMessageEvent, ConfigWindow,
+    ConfigureNotifyEvent, ConfigureRequestEvent, ConfigureWindowAux, ConnectionExt as _, EventMask,
+    GrabMode, InputFocus, ModMask, StackMode, Time, Window as X11Window, WindowEnum,
+    CONFIGURE_NOTIFY_EVENT,
+};
+
+use crate::atoms::Atoms;
+use crate::layout::Geometry;
+use crate::property;
+
+/// the ICCCM `WM_STATE` states, tells clients and other tools
+/// whether flow shows the window or not
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum WmState {
+    Withdrawn = 0,
+    Normal = 1,
+    Iconic = 3,
+}
+
+/// how flow treats a window, read from its EWMH `_NET_WM_WINDOW_TYPE`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WindowType {
+    /// a window tiled by the workspace layout
+    #[default]
+    Normal,
+
+    /// a panel or a bar, flow maps it but doesn't manage it
+    Dock,
+
+    /// dialogs, utility windows and splash screens,
+    /// they float centered on the screen
+    Dialog,
+
+    /// notifications and tooltips, they float where
+    /// they asked to be and are never focused
+    Notification,
+}
+
+impl WindowType {
+    /// reads the type of the window, a window lists its types from the most
+    /// to the least preferred and the first type flow knows is used, a
+    /// transient window without a type is a dialog, as described in EWMH
+    pub async fn read<C>(connection: &C, window: X11Window, atoms: &Atoms) -> anyhow::Result<Self>
+    where
+        C: Connection,
+    {
+        let types = property::get_u32(
+            connection,
+            window,
+            atoms._NET_WM_WINDOW_TYPE,
+            AtomEnum::ATOM.into(),
+        )
+        .await?;
+        for kind in &types {
+            if *kind == atoms._NET_WM_WINDOW_TYPE_NORMAL {
+                return Ok(WindowType::Normal);
+            } else if *kind == atoms._NET_WM_WINDOW_TYPE_DOCK {
+                return Ok(WindowType::Dock);
+            } else if [
+                atoms._NET_WM_WINDOW_TYPE_DIALOG,
+                atoms._NET_WM_WINDOW_TYPE_UTILITY,
+                atoms._NET_WM_WINDOW_TYPE_SPLASH,
+            ]
+            .contains(kind)
+            {
+                return Ok(WindowType::Dialog);
+            } else if [
+                atoms._NET_WM_WINDOW_TYPE_NOTIFICATION,
+                atoms._NET_WM_WINDOW_TYPE_TOOLTIP,
+            ]
+            .contains(kind)
+            {
+                return Ok(WindowType::Notification);
+            }
+        }
+
+        if types.is_empty() {
+            let transient_for = property::get_u32(
+                connection,
+                window,
+                AtomEnum::WM_TRANSIENT_FOR.into(),
+                AtomEnum::WINDOW.into(),
+            )
+            .await?;
+            if !transient_for.is_empty() {
+                return Ok(WindowType::Dialog);
+            }
+        }
+        Ok(WindowType::Normal)
+    }
+}
 
 pub struct Window<C>
 where
-    C: Connection
+    C: Connection,
 {
     connection: Arc<C>,
-    x11_window: X11Window
+    x11_window: X11Window,
+    kind: WindowType,
+
+    /// the last geometry flow configured the window with
+    geometry: Geometry,
+    border_width: u32,
+
+    /// how many `UnmapNotify` events are caused by flow unmapping the window,
+    /// those events should not be confused with the client withdrawing itself
+    pending_unmaps: usize,
+
+    /// the ICCCM `WM_HINTS.input` field, windows that don't accept input
+    /// are never given the input focus directly
+    accepts_input: bool,
+
+    /// true if the window supports the `WM_TAKE_FOCUS` protocol
+    takes_focus: bool,
+
+    /// the ICCCM `WM_HINTS` urgency flag, set by clients
+    /// that want the user attention
+    urgent: bool,
+
+    fullscreen: bool,
+
+    /// the geometry and border width the window gets back when it leaves
+    /// fullscreen, `None` for a window that started fullscreen and is tiled,
+    /// since the layout places it
+    restore: Option<(Geometry, u32)>,
 }
 
 impl<C> Window<C>
 where
-    C: Connection
+    C: Connection,
 {
-    pub fn new(connection: Arc<C>, window: X11Window) -> Self {
+    pub fn new(connection: Arc<C>, window: X11Window, kind: WindowType) -> Self {
         Self {
             connection,
-            x11_window: window
+            x11_window: window,
+            kind,
+            geometry: Geometry::default(),
+            border_width: 0,
+            pending_unmaps: 0,
+            accepts_input: true,
+            takes_focus: false,
+            urgent: false,
+            fullscreen: false,
+            restore: None,
         }
     }
+
+    /// selects the events flow needs from the window and grabs the mouse buttons
+    /// so clicking the window can focus it, reads the window focus hints and
+    /// whether the window asked to start fullscreen
+    pub async fn setup(&mut self, atoms: &Atoms) -> anyhow::Result<()> {
+        self.connection
+            .change_window_attributes(
+                self.x11_window,
+                &ChangeWindowAttributesAux::new()
+                    .event_mask(EventMask::ENTER_WINDOW | EventMask::PROPERTY_CHANGE),
+            )
+            .await?;
+
+        // the pointer is frozen on click until flow replays
+        // the click to the window, after focusing it
+        self.connection
+            .grab_button(
+                false,
+                self.x11_window,
+                EventMask::BUTTON_PRESS,
+                GrabMode::SYNC,
+                GrabMode::ASYNC,
+                WindowEnum::NONE,
+                WindowEnum::NONE,
+                ButtonIndex::ANY,
+                ModMask::ANY,
+            )
+            .await?;
+
+        let states = property::get_u32(
+            &*self.connection,
+            self.x11_window,
+            atoms._NET_WM_STATE,
+            AtomEnum::ATOM.into(),
+        )
+        .await?;
+        // flow hasn't configured the window yet, so there is no geometry to
+        // restore, a floating window records its own in `float`
+        self.fullscreen = states.contains(&atoms._NET_WM_STATE_FULLSCREEN);
+
+        self.update_hints(atoms).await
+    }
+
+    /// reads the ICCCM `WM_HINTS` input and urgency fields and the
+    /// `WM_TAKE_FOCUS` protocol, which decide how the window is focused
+    pub async fn update_hints(&mut self, atoms: &Atoms) -> anyhow::Result<()> {
+        // `WM_HINTS` starts with the flags field followed by the input
+        // field, the input field is only valid when the first flag bit is set
+        const INPUT_HINT: u32 = 1;
+        const URGENCY_HINT: u32 = 1 << 8;
+        let hints = property::get_u32(
+            &*self.connection,
+            self.x11_window,
+            AtomEnum::WM_HINTS.into(),
+            AtomEnum::WM_HINTS.into(),
+        )
+        .await?;
+        self.accepts_input = match hints.as_slice() {
+            [flags, input, ..] if flags & INPUT_HINT != 0 => *input != 0,
+            _ => true,
+        };
+        self.urgent = hints.first().is_some_and(|flags| flags & URGENCY_HINT != 0);
+        self.takes_focus = self.supports_protocol(atoms, atoms.WM_TAKE_FOCUS).await?;
+        Ok(())
+    }
+
+    #[inline]
+    pub fn id(&self) -> X11Window {
+        self.x11_window
+    }
+
+    #[inline]
+    pub fn geometry(&self) -> Geometry {
+        self.geometry
+    }
+
+    #[inline]
+    pub fn urgent(&self) -> bool {
+        self.urgent
+    }
+
+    /// marks the window as urgent or not, the flag is read
+    /// again from `WM_HINTS` when the client changes them
+    #[inline]
+    pub fn set_urgent(&mut self, urgent: bool) {
+        self.urgent = urgent;
+    }
+
+    /// floating windows are left out of the layout and keep their geometry
+    #[inline]
+    pub fn floating(&self) -> bool {
+        matches!(self.kind, WindowType::Dialog | WindowType::Notification)
+    }
+
+    /// false for the windows the workspace never gives the focus to
+    #[inline]
+    pub fn focusable(&self) -> bool {
+        self.kind != WindowType::Notification
+    }
+
+    /// gives a floating window the size it asked for, dialogs are centered in the
+    /// given area, notifications keep the position they asked for without a border,
+    /// a fullscreen window gets that geometry when it leaves fullscreen
+    pub async fn float(&mut self, area: Geometry, border_width: u32) -> anyhow::Result<()> {
+        let requested = self
+            .connection
+            .get_geometry(self.x11_window)
+            .await?
+            .reply()
+            .await?;
+        let border_width = match self.kind {
+            WindowType::Notification => 0,
+            _ => border_width,
+        };
+        let width = requested.width as u32 + border_width * 2;
+        let height = requested.height as u32 + border_width * 2;
+        let (x, y) = match self.kind {
+            WindowType::Dialog => (
+                area.x + (area.width as i32 - width as i32) / 2,
+                area.y + (area.height as i32 - height as i32) / 2,
+            ),
+            _ => (requested.x.into(), requested.y.into()),
+        };
+        let geometry = Geometry::new(x, y, width, height);
+        if self.fullscreen {
+            self.restore = Some((geometry, border_width));
+            return Ok(());
+        }
+        self.configure(geometry, border_width).await
+    }
+
+    /// applies the geometry a floating window asked for in a configure
+    /// request, the border width stays the one flow gave the window
+    pub async fn configure_request(&mut self, event: &ConfigureRequestEvent) -> anyhow::Result<()> {
+        let border = self.border_width * 2;
+        let mut geometry = self.geometry;
+        if event.value_mask.contains(ConfigWindow::X) {
+            geometry.x = event.x.into();
+        }
+        if event.value_mask.contains(ConfigWindow::Y) {
+            geometry.y = event.y.into();
+        }
+        if event.value_mask.contains(ConfigWindow::WIDTH) {
+            geometry.width = event.width as u32 + border;
+        }
+        if event.value_mask.contains(ConfigWindow::HEIGHT) {
+            geometry.height = event.height as u32 + border;
+        }
+        self.configure(geometry, self.border_width).await
+    }
+
+    #[inline]
+    pub fn fullscreen(&self) -> bool {
+        self.fullscreen
+    }
+
+    /// makes the window cover the given area without a border, the
+    /// window gets its current geometry back when it leaves fullscreen
+    pub async fn enter_fullscreen(&mut self, atoms: &Atoms, area: Geometry) -> anyhow::Result<()> {
+        if !self.fullscreen {
+            self.fullscreen = true;
+            self.restore = Some((self.geometry, self.border_width));
+        }
+        self.configure(area, 0).await?;
+        self.write_state(atoms).await
+    }
+
+    /// gives the window back the geometry it had before it went fullscreen,
+    /// a tiled window without one is placed by the next arrange
+    pub async fn exit_fullscreen(&mut self, atoms: &Atoms) -> anyhow::Result<()> {
+        self.fullscreen = false;
+        if let Some((geometry, border_width)) = self.restore.take() {
+            self.configure(geometry, border_width).await?;
+        }
+        self.write_state(atoms).await
+    }
+
+    /// adds or removes the fullscreen atom in the window EWMH `_NET_WM_STATE`
+    async fn write_state(&self, atoms: &Atoms) -> anyhow::Result<()> {
+        self.set_state(atoms, atoms._NET_WM_STATE_FULLSCREEN, self.fullscreen)
+            .await
+    }
+
+    /// adds or removes the given atom in the window EWMH `_NET_WM_STATE`,
+    /// the other states set by the client are kept
+    pub async fn set_state(&self, atoms: &Atoms, state: Atom, enabled: bool) -> anyhow::Result<()> {
+        let mut states = property::get_u32(
+            &*self.connection,
+            self.x11_window,
+            atoms._NET_WM_STATE,
+            AtomEnum::ATOM.into(),
+        )
+        .await?;
+        if states.contains(&state) == enabled {
+            return Ok(());
+        }
+
+        states.retain(|&atom| atom != state);
+        if enabled {
+            states.push(state);
+        }
+        property::set_u32(
+            &*self.connection,
+            self.x11_window,
+            atoms._NET_WM_STATE,
+            AtomEnum::ATOM.into(),
+            &states,
+        )
+        .await
+    }
+
+    /// moves and resizes the window so it with its border will
+    /// fit exactly inside the given geometry
+    pub async fn configure(&mut self, geometry: Geometry, border_width: u32) -> anyhow::Result<()> {
+        self.geometry = geometry;
+        self.border_width = border_width;
+        self.connection
+            .configure_window(
+                self.x11_window,
+                &ConfigureWindowAux {
+                    x: Some(geometry.x),
+                    y: Some(geometry.y),
+                    width: Some(geometry.width.saturating_sub(border_width * 2).max(1)),
+                    height: Some(geometry.height.saturating_sub(border_width * 2).max(1)),
+                    border_width: Some(border_width),
+                    sibling: None,
+                    stack_mode: None,
+                },
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// a managed window can't choose its own geometry, so when a client asks
+    /// to configure itself we tell it the geometry it already has, as described in ICCCM
+    pub async fn send_configure_notify(&self) -> anyhow::Result<()> {
+        let event = ConfigureNotifyEvent {
+            response_type: CONFIGURE_NOTIFY_EVENT,
+            sequence: 0,
+            event: self.x11_window,
+            window: self.x11_window,
+            above_sibling: WindowEnum::NONE.into(),
+            x: self.geometry.x as i16,
+            y: self.geometry.y as i16,
+            width: self
+                .geometry
+                .width
+                .saturating_sub(self.border_width * 2)
+                .max(1) as u16,
+            height: self
+                .geometry
+                .height
+                .saturating_sub(self.border_width * 2)
+                .max(1) as u16,
+            border_width: self.border_width as u16,
+            override_redirect: false,
+        };
+        self.connection
+            .send_event(false, self.x11_window, EventMask::STRUCTURE_NOTIFY, event)
+            .await?;
+        Ok(())
+    }
+
+    /// maps the window and marks it with the `Normal` state
+    pub async fn map(&self, atoms: &Atoms) -> anyhow::Result<()> {
+        self.set_wm_state(atoms, WmState::Normal).await?;
+        self.connection.map_window(self.x11_window).await?;
+        Ok(())
+    }
+
+    /// unmaps the window, marks it with the `Iconic` state and remembers that
+    /// the next `UnmapNotify` event for this window was caused by flow
+    pub async fn unmap(&mut self, atoms: &Atoms) -> anyhow::Result<()> {
+        self.pending_unmaps += 1;
+        self.set_wm_state(atoms, WmState::Iconic).await?;
+        self.connection.unmap_window(self.x11_window).await?;
+        Ok(())
+    }
+
+    /// sets the window ICCCM `WM_STATE` property, flow never sets an icon window
+    pub async fn set_wm_state(&self, atoms: &Atoms, state: WmState) -> anyhow::Result<()> {
+        property::set_u32(
+            &*self.connection,
+            self.x11_window,
+            atoms.WM_STATE,
+            atoms.WM_STATE,
+            &[state as u32, WindowEnum::NONE.into()],
+        )
+        .await
+    }
+
+    /// returns true if the received `UnmapNotify` event was
+    /// caused by flow and not by the client
+    pub fn take_pending_unmap(&mut self) -> bool {
+        if self.pending_unmaps > 0 {
+            self.pending_unmaps -= 1;
+            return true;
+        }
+        false
+    }
+
+    /// gives the window the input focus and raises it above the other windows, the
+    /// focus is given based on the ICCCM input models, windows that don't accept input
+    /// are only sent `WM_TAKE_FOCUS` if they support it
+    pub async fn focus(&self, atoms: &Atoms) -> anyhow::Result<()> {
+        if self.accepts_input {
+            self.connection
+                .set_input_focus(
+                    InputFocus::POINTER_ROOT,
+                    self.x11_window,
+                    Time::CURRENT_TIME,
+                )
+                .await?;
+        }
+        if self.takes_focus {
+            self.send_protocol(atoms, atoms.WM_TAKE_FOCUS).await?;
+        }
+        self.raise().await
+    }
+
+    /// stacks the window above its siblings
+    pub async fn raise(&self) -> anyhow::Result<()> {
+        self.connection
+            .configure_window(
+                self.x11_window,
+                &ConfigureWindowAux::new().stack_mode(StackMode::ABOVE),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// forcefully disconnects the client that owns the window
+    pub async fn kill(&self) -> anyhow::Result<()> {
+        self.connection.kill_client(self.x11_window).await?;
+        Ok(())
+    }
+
+    /// returns true if the window listed the given protocol atom
+    /// in its `WM_PROTOCOLS` property
+    pub async fn supports_protocol(&self, atoms: &Atoms, protocol: u32) -> anyhow::Result<bool> {
+        let protocols = property::get_u32(
+            &*self.connection,
+            self.x11_window,
+            atoms.WM_PROTOCOLS,
+            AtomEnum::ATOM.into(),
+        )
+        .await?;
+        Ok(protocols.contains(&protocol))
+    }
+
+    /// reads the window title, the EWMH `_NET_WM_NAME` is preferred
+    /// since it is always utf8, `WM_NAME` is used as a fallback
+    pub async fn title(&self, atoms: &Atoms) -> anyhow::Result<String> {
+        let title = property::get_string(
+            &*self.connection,
+            self.x11_window,
+            atoms._NET_WM_NAME,
+            atoms.UTF8_STRING,
+        )
+        .await?;
+        if let Some(title) = title {
+            return Ok(title);
+        }
+
+        let title = property::get_string(
+            &*self.connection,
+            self.x11_window,
+            AtomEnum::WM_NAME.into(),
+            AtomEnum::ANY.into(),
+        )
+        .await?;
+        Ok(title.unwrap_or_default())
+    }
+
+    /// sends a `WM_PROTOCOLS` client message with the given protocol, as described in ICCCM
+    pub async fn send_protocol(&self, atoms: &Atoms, protocol: u32) -> anyhow::Result<()> {
+        let event = ClientMessageEvent::new(
+            32,
+            self.x11_window,
+            atoms.WM_PROTOCOLS,
+            [protocol, Time::CURRENT_TIME.into(), 0, 0, 0],
+        );
+        self.connection
+            .send_event(false, self.x11_window, EventMask::NO_EVENT, event)
+            .await?;
+        Ok(())
+    }
+}
+
+impl<C> Window<C>
+where
+    C: Connection + Send + Sync + 'static,
+{
+    /// asks the window to close itself with `WM_DELETE_WINDOW` so the client
+    /// can prompt about unsaved work, if the client doesn't support the protocol
+    /// it is killed, with a timeout the client is also killed when the window is
+    /// still shown after the timeout
+    pub async fn close(&self, atoms: &Atoms, timeout: Option<Duration>) -> anyhow::Result<()> {
+        if !self
+            .supports_protocol(atoms, atoms.WM_DELETE_WINDOW)
+            .await?
+        {
+            log::debug!(
+                "window {} doesn't support `WM_DELETE_WINDOW`, killing it",
+                self.x11_window
+            );
+            return self.kill().await;
+        }
+
+        self.send_protocol(atoms, atoms.WM_DELETE_WINDOW).await?;
+        let Some(timeout) = timeout else {
+            return Ok(());
+        };
+
+        // waiting in the background so the event loop can continue
+        // handling events, including the window destruction
+        let connection = Arc::clone(&self.connection);
+        let window = self.x11_window;
+        let atoms = *atoms;
+        tokio::spawn(async move {
+            tokio::time::sleep(timeout).await;
+
+            // if the window was destroyed the request fails, which means the
+            // client closed on its own, a withdrawn window was closed as well
+            // even though the client keeps it around
+            let state =
+                property::get_u32(&*connection, window, atoms.WM_STATE, atoms.WM_STATE).await;
+            let shown = match state {
+                Ok(state) => state
+                    .first()
+                    .is_some_and(|&state| state != WmState::Withdrawn as u32),
+                Err(_) => false,
+            };
+
+            if shown {
+                log::info!(
+                    "window {} didn't close after {:?}, killing it",
+                    window,
+                    timeout
+                );
+                if let Err(err) = connection.kill_client(window).await {
+                    log::error!("couldn't kill window {}, {}", window, err);
+                }
+            }
+        });
+        Ok(())
+    }
 }
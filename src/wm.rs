@@ -1,7 +1,8 @@
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 use std::collections::HashSet;
+use std::path::PathBuf;
 
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, Notify, broadcast};
 use tokio::task::JoinSet;
 
 use x11rb_async::errors::ReplyError;
@@ -9,23 +10,39 @@ use x11rb_async::connection::Connection;
 use x11rb_async::protocol::xkb::ConnectionExt as _;
 use x11rb_async::protocol::xproto::{
     ConnectionExt as _, ChangeWindowAttributesAux, ConfigureWindowAux, EventMask, GrabMode,
-    KeyPressEvent, KeyReleaseEvent, MapRequestEvent, ConfigureRequestEvent, ModMask, Window,
-    PropMode, Property, Atom, AtomEnum
+    KeyPressEvent, KeyReleaseEvent, MapRequestEvent, MapState, ConfigureRequestEvent, ModMask,
+    Window, AtomEnum, UnmapNotifyEvent, DestroyNotifyEvent, EnterNotifyEvent, ButtonPressEvent,
+    NotifyMode, Allow, InputFocus, Time, PropertyNotifyEvent, ClientMessageEvent, StackMode,
 };
 use x11rb_async::protocol::{ErrorKind, Event};
-use xkbcommon::xkb;
 
 use crate::Config;
-use crate::key::{Key, KeyState};
-use crate::workspace::Workspace;
+use crate::action::Action;
+use crate::atoms::Atoms;
+use crate::check::{self, Problem};
+use crate::ewmh::Ewmh;
+use crate::config::{ActivationPolicy, ConfigCombo, FocusMode};
+use crate::key::{Key, KeyPress, KeyState};
+use crate::property;
+use crate::selection::ManagerSelection;
+use crate::window::{Window as XWindow, WindowType, WmState};
+use crate::workspace::{Workspace, Workspaces, WORKSPACES_COUNT};
+use crate::layout::{Geometry, LayoutKind};
 use crate::combos::{ComboTree, ComboRecord};
-use crate::combos::handlers::Spawn;
+use crate::combos::handlers::{Builtin, ComboHandler, Spawn};
+use crate::ipc::{Event as IpcEvent, Tree, WindowTree, WorkspaceTree};
 
 pub struct WindowManager<C>
 where
     C: Connection + Sync + Send + 'static,
 {
-    config: Config,
+    /// the config is replaced as a whole when it is reloaded, so readers
+    /// take a cheap clone of the `Arc` instead of holding the lock
+    config: RwLock<Arc<Config>>,
+
+    /// the file the config was read from, reloading reads it again, when
+    /// flow started with the built-in config the file is searched again
+    config_path: Option<PathBuf>,
 
     /// connection is shared across other entities that may
     /// use it like `Window` and `Workspace`, since those entities are managed
@@ -37,21 +54,46 @@ where
     /// of all other windows
     root: Window,
 
+    /// the `WM_S<screen>` selection flow owns while it manages the screen
+    selection: ManagerSelection,
+
+    /// atoms interned when the window manager started
+    atoms: Atoms,
+
+    /// the EWMH root properties, kept in sync with the managed windows
+    ewmh: Ewmh<C>,
+
+    /// the root window geometry, the workspaces layouts place windows in
+    /// the part of it the docks leave, fullscreen windows cover all of it
+    screen: Geometry,
+
     /// key state of the current connection layout
     keystate: KeyState,
-    workspaces: [Workspace<C>; 9],
+    workspaces: Mutex<Workspaces<C>>,
 
     /// loads the combination from the `Config` and creates
     /// a tree like structure that maps to the combo handler that was also
     /// defined in the Config
-    combos_tree: Mutex<ComboTree>,
+    combos_tree: Mutex<ComboTree<C>>,
 
     /// record the received key presses into the combo record
     /// and pass it to the combo tree to trigger the correct
     /// handler based on the combination
     combos_record: Mutex<ComboRecord>,
+
+    /// notified when the window manager should stop, the event
+    /// loop in `run` will return after the notification
+    shutdown: Notify,
+
+    /// broadcasts the state changes to the ipc clients that
+    /// subscribed to them, sending without subscribers is a no-op
+    events: broadcast::Sender<IpcEvent>,
 }
 
+/// the number of events a slow subscriber can lag behind
+/// before it starts missing events
+const EVENTS_CAPACITY: usize = 64;
+
 impl<C> WindowManager<C>
 where
     C: Connection + Sync + Send,
@@ -63,8 +105,21 @@ where
         connection: C,
         root: Window,
         config: Config,
+        config_path: Option<PathBuf>,
+        replace: bool,
     ) -> anyhow::Result<Self> {
         let connection = Arc::new(connection);
+        let atoms = Atoms::intern(&*connection).await?;
+
+        let screen_number = connection
+            .setup()
+            .roots
+            .iter()
+            .position(|screen| screen.root == root)
+            .ok_or_else(|| anyhow::anyhow!("window {} is not a root window", root))?;
+        let selection =
+            ManagerSelection::acquire(&*connection, root, screen_number, &atoms, replace).await?;
+
         connection
             .change_window_attributes(
                 root,
@@ -75,39 +130,171 @@ where
             .check()
             .await
             .inspect_err(|err| {
-                if let ReplyError::X11Error(err) = err {
-                    if err.error_kind == ErrorKind::Access {
-                        log::error!(
-                            "window manager already runnig, couldn't request event from x11 server"
-                        );
-                    }
+                // a window manager that doesn't follow the ICCCM
+                // can run without owning the manager selection
+                if let ReplyError::X11Error(err) = err
+                    && err.error_kind == ErrorKind::Access
+                {
+                    log::error!(
+                        "window manager already runnig, couldn't request event from x11 server"
+                    );
                 }
             })?;
 
+        let ewmh = Ewmh::setup(connection.clone(), root, atoms).await?;
         connection.xkb_use_extension(1, 0).await?;
         let keystate = KeyState::from_connection(&*connection).await?;
 
-        let workspaces = [
-                Workspace::with_connection(connection.clone()),
-                Workspace::with_connection(connection.clone()),
-                Workspace::with_connection(connection.clone()),
-                Workspace::with_connection(connection.clone()),
-                Workspace::with_connection(connection.clone()),
-                Workspace::with_connection(connection.clone()),
-                Workspace::with_connection(connection.clone()),
-                Workspace::with_connection(connection.clone()),
-                Workspace::with_connection(connection.clone()),
-        ];
-
-        Ok(WindowManager {
-            config,
+        let root_geometry = connection.get_geometry(root).await?.reply().await?;
+        let screen = Geometry::new(
+            0,
+            0,
+            root_geometry.width as u32,
+            root_geometry.height as u32,
+        );
+
+        let workspaces = std::array::from_fn(|_| {
+            Workspace::with_connection(
+                connection.clone(),
+                config.layout().default_layout().create(config.layout()),
+            )
+        });
+
+        let wm = WindowManager {
+            config: RwLock::new(Arc::new(config)),
+            config_path,
             connection,
             root,
+            selection,
+            atoms,
+            ewmh,
+            screen,
             keystate,
-            workspaces,
+            workspaces: Mutex::new(Workspaces::new(workspaces)),
             combos_tree: Mutex::new(ComboTree::default()),
             combos_record: Mutex::new(ComboRecord::default()),
-        })
+            shutdown: Notify::new(),
+            events: broadcast::channel(EVENTS_CAPACITY).0,
+        };
+        wm.adopt_windows().await?;
+        Ok(wm)
+    }
+
+    /// manages the windows that already exist when flow starts, so restarting
+    /// the window manager doesn't leave orphaned windows, windows hidden by a
+    /// previous window manager are marked as `Iconic` and are adopted as well
+    async fn adopt_windows(&self) -> anyhow::Result<()> {
+        let tree = self.connection.query_tree(self.root).await?.reply().await?;
+
+        let mut workspaces = self.workspaces.lock().await;
+        let current = workspaces.current_index();
+
+        for window in tree.children {
+            // the window could be destroyed since the tree was queried
+            match self.adopt_window(&mut workspaces, window, current).await {
+                Err(err) if is_x11_error(&err) => {
+                    log::debug!("window {} is gone before it was adopted", window);
+                    if workspaces.unmanage(window).is_some() {
+                        self.ewmh.remove_client(window).await?;
+                    }
+                    self.ewmh.remove_dock(window);
+                }
+                adopted => adopted?,
+            }
+        }
+
+        let workspace = workspaces.current_mut();
+        self.arrange(workspace).await?;
+        workspace.show(&self.atoms).await?;
+        self.refocus(&mut workspaces).await
+    }
+
+    /// adopts a single window found when flow starts, the window is placed in
+    /// the workspace it was on if it says so, or in the current workspace
+    async fn adopt_window(
+        &self,
+        workspaces: &mut Workspaces<C>,
+        window: Window,
+        current: usize,
+    ) -> anyhow::Result<()> {
+        let attributes = self
+            .connection
+            .get_window_attributes(window)
+            .await?
+            .reply()
+            .await?;
+
+        if attributes.override_redirect {
+            return Ok(());
+        }
+
+        let viewable = attributes.map_state == MapState::VIEWABLE;
+        let state = property::get_u32(
+            &*self.connection,
+            window,
+            self.atoms.WM_STATE,
+            self.atoms.WM_STATE,
+        )
+        .await?;
+        if !viewable && state.first() != Some(&(WmState::Iconic as u32)) {
+            return Ok(());
+        }
+
+        // docks are left alone, but a dock hidden by the previous window
+        // manager is shown again since flow never hides docks
+        let kind = WindowType::read(&*self.connection, window, &self.atoms).await?;
+        if kind == WindowType::Dock {
+            self.ewmh.add_dock(window, self.screen).await?;
+            if !viewable {
+                self.connection.map_window(window).await?;
+            }
+            return Ok(());
+        }
+
+        let index = property::get_u32(
+            &*self.connection,
+            window,
+            self.atoms._NET_WM_DESKTOP,
+            AtomEnum::CARDINAL.into(),
+        )
+        .await?
+        .first()
+        .map(|&desktop| desktop as usize)
+        .filter(|&desktop| desktop < WORKSPACES_COUNT)
+        .unwrap_or(current);
+
+        log::debug!("adopting window {} into workspace {}", window, index + 1);
+        let Some(window) = workspaces.manage(window, kind, index) else {
+            return Ok(());
+        };
+        window.setup(&self.atoms).await?;
+        if window.floating() {
+            window
+                .float(
+                    self.ewmh.work_area(self.screen),
+                    self.config().layout().border_width(),
+                )
+                .await?;
+        }
+        self.ewmh.add_client(window.id()).await?;
+        self.ewmh.set_window_desktop(window.id(), index).await?;
+
+        if index != current {
+            // only a mapped window will report an `UnmapNotify`
+            if viewable {
+                window.unmap(&self.atoms).await?;
+            } else {
+                window.set_wm_state(&self.atoms, WmState::Iconic).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// returns the currently used config
+    #[inline]
+    pub fn config(&self) -> Arc<Config> {
+        // the lock is only held to clone the `Arc`, it can't be poisoned
+        Arc::clone(&self.config.read().unwrap())
     }
 
     /// running the window manager will register for keybinds defined in the
@@ -116,96 +303,151 @@ where
         self.clone().setup_binds().await?;
 
         loop {
-            match self.connection.wait_for_event().await? {
-                Event::KeyPress(event) => self.handle_key_press_event(event).await,
-                Event::KeyRelease(event) => self.handle_key_release_event(event).await,
-                Event::MapRequest(event) => self.handle_map_request_event(event).await?,
-                Event::ConfigureRequest(event) => {
-                    self.connection
-                        .configure_window(
-                            event.window,
-                            &ConfigureWindowAux {
-                                x: Some(event.x as i32),
-                                y: Some(event.y as i32),
-                                width: Some(event.width as u32),
-                                height: Some(event.height as u32),
-                                border_width: Some(event.border_width as u32),
-                                sibling: Some(event.sibling),
-                                stack_mode: Some(event.stack_mode),
-                            },
-                        )
-                        .await?;
+            let event = tokio::select! {
+                event = self.connection.wait_for_event() => event?,
+                _ = self.shutdown.notified() => {
+                    log::info!("shutting down the window manager");
+                    return Ok(());
+                }
+            };
+
+            let handled = match event {
+                Event::KeyPress(event) => {
+                    self.handle_key_press_event(event).await;
+                    Ok(())
                 }
-                _ => {}
+                Event::KeyRelease(event) => {
+                    self.handle_key_release_event(event).await;
+                    Ok(())
+                }
+                Event::MapRequest(event) => self.handle_map_request_event(event).await,
+                Event::UnmapNotify(event) => self.handle_unmap_notify_event(event).await,
+                Event::DestroyNotify(event) => self.handle_destroy_notify_event(event).await,
+                Event::EnterNotify(event) => self.handle_enter_notify_event(event).await,
+                Event::ButtonPress(event) => self.handle_button_press_event(event).await,
+                Event::ConfigureRequest(event) => self.handle_configure_request_event(event).await,
+                Event::PropertyNotify(event) => self.handle_property_notify_event(event).await,
+                Event::ClientMessage(event) => self.handle_client_message_event(event).await,
+                Event::SelectionClear(event) if self.selection.is_lost(&event) => {
+                    log::info!("another window manager replaced flow, shutting down");
+                    self.ewmh.release().await?;
+                    self.selection.release(&*self.connection, self.root).await?;
+                    return Ok(());
+                }
+                _ => Ok(()),
+            };
+
+            // a window can be destroyed before its events are handled, the
+            // requests about it then fail with an x11 error, which must not
+            // stop the window manager, only connection errors do
+            match handled {
+                Err(err) if is_x11_error(&err) => {
+                    log::debug!("ignoring x11 error while handling an event, {}", err)
+                }
+                handled => handled?,
             }
         }
-        Ok(())
     }
 
-    /// this method is temporarly here for now, in future need to check
-    /// when the config file is edited and refresh the binds
-    async fn setup_binds(self: Arc<Self>) -> anyhow::Result<()> {
-        // here for future, when `setup_binds` will be
-        // called multiple times
-        self.combos_tree.lock().await.clear();
-        self.connection
-            .ungrab_key(0, self.root, ModMask::ANY)
-            .await?
-            .check()
-            .await?;
-
-        let mut root_keycodes = HashSet::new();
+    /// builds the combos of the given config, the config is checked first and
+    /// the combos with problems are left out, the returned key presses are
+    /// the first press of each combo, the ones to grab
+    fn build_combos(&self, config: &Config) -> (ComboTree<C>, HashSet<KeyPress>, Vec<Problem>) {
+        let mut tree = ComboTree::default();
+        let mut root_presses = HashSet::new();
+        let problems = check::check(config, Some(&self.keystate));
+        let invalid: HashSet<&str> = problems
+            .iter()
+            .flat_map(|problem| problem.combos.iter().map(String::as_str))
+            .collect();
 
         // iterator on the config binds, for each bind we register the
-        // combo and add the chars to the `keycodes_to_register` set
+        // combo and add the first key to the `root_presses` set
         // so we will later request those key press events from the X server
-        for (name, config_combo) in self.config.combos() {
-            let keycode_combo: Vec<xkb::Keycode> = config_combo
+        for (name, config_combo) in config.combos() {
+            if invalid.contains(name.as_str()) {
+                continue;
+            }
+            let Some(handler) = Self::combo_handler(name, config_combo) else {
+                continue;
+            };
+            let Some(combo) = config_combo
                 .keys()
                 .iter()
-                .filter_map(|key| {
-                    key.chars().next().and_then(|c| {
-                        let key = Key::from(c);
-                        key.keycode(&self.keystate)
-                    })
-                })
-                .collect();
-
-            self.combos_tree.lock().await.add_combo(
-                &keycode_combo,
-                Arc::new(Spawn::new(
-                    name.clone(),
-                    "alacritty".to_string(),
-                    Vec::new(),
-                )),
-            );
+                .map(|key| key.parse::<Key>().ok()?.press(&self.keystate))
+                .collect::<Option<Vec<KeyPress>>>()
+            else {
+                continue;
+            };
 
             // we only need to register the first key in the combo
             // and x11 will report all keypresses while the root
             // key is pressed first
-            root_keycodes.insert(keycode_combo.first().take().unwrap().clone());
+            if let Some(&root_press) = combo.first() {
+                root_presses.insert(root_press);
+                tree.add_combo(&combo, handler);
+            }
+        }
+        (tree, root_presses, problems)
+    }
+
+    /// registers the combos of the current config and grabs their keys, the
+    /// invalid combos are ignored so a single typo doesn't leave flow
+    /// without any combo
+    async fn setup_binds(self: Arc<Self>) -> anyhow::Result<()> {
+        let (tree, root_presses, problems) = self.build_combos(&self.config());
+        for problem in problems {
+            log::error!("{}, ignoring it", problem.message);
         }
+        self.install_binds(tree, root_presses).await
+    }
+
+    /// replaces the registered combos with the given tree and
+    /// grabs the given keys instead of the previously grabbed keys
+    async fn install_binds(
+        self: Arc<Self>,
+        tree: ComboTree<C>,
+        root_presses: HashSet<KeyPress>,
+    ) -> anyhow::Result<()> {
+        *self.combos_tree.lock().await = tree;
+        self.connection
+            .ungrab_key(0, self.root, ModMask::ANY)
+            .await?
+            .check()
+            .await?;
 
         let mut tasks = JoinSet::<anyhow::Result<()>>::new();
+        let modifier = u16::from(ModMask::from(self.config().flow().modifier()));
+        let ignored = ignored_modifiers(modifier);
 
-        // create an async task for each key that is needed to be grabbed
-        for keycode in root_keycodes {
-            let wm = Arc::clone(&self);
-            tasks.spawn(async move {
-                wm.connection
-                    .grab_key(
-                        true,
-                        wm.root,
-                        wm.config.flow().modifier().into(),
-                        keycode,
-                        GrabMode::ASYNC,
-                        GrabMode::ASYNC,
-                    )
-                    .await?
-                    .check()
-                    .await?;
-                Ok(())
-            });
+        // create an async task for each key that is needed to be grabbed, the
+        // key is grabbed with every combination of the ignored lock modifiers
+        // so combos still work while caps lock or num lock are on
+        for press in root_presses {
+            let base = modifier | u16::from(press.modifiers());
+            let masks: HashSet<u16> = [0, ignored & LOCK_MASK, ignored & NUM_LOCK_MASK, ignored]
+                .into_iter()
+                .map(|lock| base | lock)
+                .collect();
+
+            for mask in masks {
+                let wm = Arc::clone(&self);
+                tasks.spawn(async move {
+                    wm.connection
+                        .grab_key(
+                            true,
+                            wm.root,
+                            mask.into(),
+                            press.keycode(),
+                            GrabMode::ASYNC,
+                            GrabMode::ASYNC,
+                        )
+                        .await?
+                        .check()
+                        .await?;
+                    Ok(())
+                });
+            }
         }
 
         // wait for all grab keys to finish
@@ -215,22 +457,433 @@ where
         Ok(())
     }
 
+    /// creates the handler for the given combo config, a combo that passed
+    /// the config check either spawns a process or executes a built-in action
+    fn combo_handler(name: &str, combo: &ConfigCombo) -> Option<Arc<dyn ComboHandler<C>>> {
+        if let Some((program, arguments)) = combo.spawn().and_then(|spawn| spawn.program_and_args())
+        {
+            let handler = Spawn::new(name.to_string(), program, arguments)
+                .with_env(combo.env().clone())
+                .with_cwd(combo.cwd().map(|cwd| cwd.to_path_buf()));
+            return Some(Arc::new(handler));
+        }
+        let action = combo.action()?;
+        Some(Arc::new(Builtin::new(name.to_string(), action.clone())))
+    }
+
+    /// executes the given built-in action, actions can be triggered
+    /// from combos or from other sources that control the window manager
+    pub async fn execute(self: &Arc<Self>, action: &Action) -> anyhow::Result<()> {
+        match action {
+            Action::ViewWorkspace(n) => self.view_workspace(workspace_index(*n)?).await,
+            Action::MoveToWorkspace(n) => self.move_to_workspace(workspace_index(*n)?).await,
+            Action::WorkspaceBackAndForth => {
+                let previous = self.workspaces.lock().await.previous_index();
+                self.view_workspace(previous).await
+            }
+            Action::Close => self.close_focused().await,
+            Action::FocusNext => self.focus_relative(1).await,
+            Action::FocusPrevious => self.focus_relative(-1).await,
+            Action::SwapMaster => self.swap_master().await,
+            Action::ToggleFullscreen => self.toggle_fullscreen().await,
+            Action::NextLayout => {
+                let kind = self.workspaces.lock().await.current_mut().layout_kind();
+                self.set_layout(kind.next()).await
+            }
+            Action::SetLayout(kind) => self.set_layout(*kind).await,
+            Action::Reload => self.clone().reload().await,
+            Action::Quit => {
+                self.shutdown.notify_one();
+                Ok(())
+            }
+        }
+    }
+
+    /// returns a receiver for the events emitted from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<IpcEvent> {
+        self.events.subscribe()
+    }
+
     #[inline]
-    async fn handle_key_press_event(&self, event: KeyPressEvent) {
+    fn emit(&self, event: IpcEvent) {
+        let _ = self.events.send(event);
+    }
+
+    /// a snapshot of all the workspaces and the windows they hold
+    pub async fn tree(&self) -> anyhow::Result<Tree> {
+        let workspaces = self.workspaces.lock().await;
+        let mut tree = Tree {
+            workspaces: Vec::with_capacity(WORKSPACES_COUNT),
+        };
+
+        for (index, workspace) in workspaces.iter().enumerate() {
+            let mut windows = Vec::with_capacity(workspace.windows().len());
+            for window in workspace.windows() {
+                // a window destroyed since flow last heard of it is left out
+                let title = match window.title(&self.atoms).await {
+                    Err(err) if is_x11_error(&err) => continue,
+                    title => title?,
+                };
+                windows.push(WindowTree {
+                    id: window.id(),
+                    title,
+                    geometry: window.geometry(),
+                });
+            }
+
+            tree.workspaces.push(WorkspaceTree {
+                number: index + 1,
+                current: index == workspaces.current_index(),
+                layout: workspace.layout_kind(),
+                focused: workspace.focused().map(|window| window.id()),
+                windows,
+            });
+        }
+        Ok(tree)
+    }
+
+    /// re-tiles the given workspace with the current config
+    #[inline]
+    async fn arrange(&self, workspace: &mut Workspace<C>) -> anyhow::Result<()> {
+        workspace
+            .arrange(
+                self.screen,
+                self.ewmh.work_area(self.screen),
+                self.config().layout().border_width(),
+            )
+            .await
+    }
+
+    /// gives the focus to the given window of the current workspace
+    async fn focus(&self, workspaces: &mut Workspaces<C>, window: Window) -> anyhow::Result<()> {
+        let workspace = workspaces.current_mut();
+        if workspace.set_focused(window)
+            && let Some(window) = workspace.focused()
+        {
+            window.focus(&self.atoms).await?;
+            self.ewmh.raise(window.id()).await?;
+            self.ewmh.set_active_window(Some(window.id())).await?;
+            self.emit(IpcEvent::WindowFocused {
+                window: Some(window.id()),
+            });
+            self.restack(workspace).await?;
+            self.clear_urgent(workspaces).await?;
+        }
+        Ok(())
+    }
+
+    /// gives the focus back to the focused window of the current workspace, if
+    /// the workspace has no windows the focus is given to the root window
+    async fn refocus(&self, workspaces: &mut Workspaces<C>) -> anyhow::Result<()> {
+        let workspace = workspaces.current_mut();
+        match workspace.focused() {
+            Some(window) => {
+                window.focus(&self.atoms).await?;
+                self.ewmh.raise(window.id()).await?;
+            }
+            None => {
+                self.connection
+                    .set_input_focus(InputFocus::POINTER_ROOT, self.root, Time::CURRENT_TIME)
+                    .await?;
+            }
+        }
+        let focused = workspace.focused().map(|window| window.id());
+        self.ewmh.set_active_window(focused).await?;
+        self.emit(IpcEvent::WindowFocused { window: focused });
+        self.restack(workspace).await?;
+        self.clear_urgent(workspaces).await
+    }
+
+    /// the focused window of the current workspace has the user
+    /// attention, so it is no longer urgent
+    async fn clear_urgent(&self, workspaces: &mut Workspaces<C>) -> anyhow::Result<()> {
+        let index = workspaces.current_index();
+        let workspace = workspaces.current_mut();
+        let Some(focused) = workspace.focused().map(|window| window.id()) else {
+            return Ok(());
+        };
+        if let Some(window) = workspace.window_mut(focused)
+            && window.urgent()
+        {
+            window.set_urgent(false);
+            window
+                .set_state(
+                    &self.atoms,
+                    self.atoms._NET_WM_STATE_DEMANDS_ATTENTION,
+                    false,
+                )
+                .await?;
+            self.emit(IpcEvent::WindowUrgent {
+                window: focused,
+                workspace: index + 1,
+                urgent: false,
+            });
+        }
+        Ok(())
+    }
+
+    /// keeps the docks above the tiled windows, the floating windows of the
+    /// workspace above the docks and the fullscreen windows above all of
+    /// them, the focused window stays above the other windows of its kind
+    async fn restack(&self, workspace: &Workspace<C>) -> anyhow::Result<()> {
+        for dock in self.ewmh.docks() {
+            self.connection
+                .configure_window(
+                    dock,
+                    &ConfigureWindowAux::new().stack_mode(StackMode::ABOVE),
+                )
+                .await?;
+        }
+
+        let focused = workspace.focused().map(|window| window.id());
+        let mut windows: Vec<&XWindow<C>> = workspace
+            .windows()
+            .iter()
+            .filter(|w| w.floating() || w.fullscreen())
+            .collect();
+        windows.sort_by_key(|w| (w.fullscreen(), Some(w.id()) == focused));
+
+        for window in windows {
+            window.raise().await?;
+            self.ewmh.raise(window.id()).await?;
+        }
+        Ok(())
+    }
+
+    async fn toggle_fullscreen(&self) -> anyhow::Result<()> {
+        let focused = self
+            .workspaces
+            .lock()
+            .await
+            .current_mut()
+            .focused()
+            .map(|window| (window.id(), window.fullscreen()));
+        match focused {
+            Some((window, fullscreen)) => self.set_fullscreen(window, !fullscreen).await,
+            None => Ok(()),
+        }
+    }
+
+    /// makes the window cover the screen or gives it back its place, fullscreen
+    /// windows are taken out of the layout and kept above the other windows
+    async fn set_fullscreen(&self, window: Window, fullscreen: bool) -> anyhow::Result<()> {
+        let mut workspaces = self.workspaces.lock().await;
+        let Some(index) = workspaces.workspace_of(window) else {
+            return Ok(());
+        };
+        let Some(managed) = workspaces.window_mut(window) else {
+            return Ok(());
+        };
+        if managed.fullscreen() == fullscreen {
+            return Ok(());
+        }
+
+        if fullscreen {
+            log::debug!("window {} enters fullscreen", window);
+            managed.enter_fullscreen(&self.atoms, self.screen).await?;
+        } else {
+            log::debug!("window {} leaves fullscreen", window);
+            managed.exit_fullscreen(&self.atoms).await?;
+        }
+
+        if index == workspaces.current_index() {
+            let workspace = workspaces.current_mut();
+            self.arrange(workspace).await?;
+            self.restack(workspace).await?;
+        }
+        Ok(())
+    }
+
+    async fn close_focused(&self) -> anyhow::Result<()> {
+        let mut workspaces = self.workspaces.lock().await;
+        if let Some(window) = workspaces.current_mut().focused() {
+            log::debug!("closing window {}", window.id());
+            window
+                .close(&self.atoms, self.config().flow().close_timeout())
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// moves the input focus `offset` windows away from
+    /// the focused window in the current workspace
+    async fn focus_relative(&self, offset: isize) -> anyhow::Result<()> {
+        let mut workspaces = self.workspaces.lock().await;
+        let cycled = workspaces
+            .current_mut()
+            .cycle(offset)
+            .map(|window| window.id());
+        if let Some(window) = cycled {
+            self.focus(&mut workspaces, window).await?;
+        }
+        Ok(())
+    }
+
+    async fn swap_master(&self) -> anyhow::Result<()> {
+        let mut workspaces = self.workspaces.lock().await;
+        let workspace = workspaces.current_mut();
+        workspace.swap_focused_with_master();
+        self.arrange(workspace).await
+    }
+
+    async fn set_layout(&self, kind: LayoutKind) -> anyhow::Result<()> {
+        let mut workspaces = self.workspaces.lock().await;
+        let workspace = workspaces.current_mut();
+        log::debug!("switching layout to `{}`", kind);
+        workspace.set_layout(kind.create(self.config().layout()));
+        self.arrange(workspace).await?;
+        self.emit(IpcEvent::LayoutChanged {
+            workspace: workspaces.current_index() + 1,
+            layout: kind,
+        });
+        Ok(())
+    }
+
+    /// reads the config file again and applies it, the new config replaces
+    /// the current one only if it is valid, otherwise the current config
+    /// stays in use and the problems are returned
+    async fn reload(self: Arc<Self>) -> anyhow::Result<()> {
+        let path = match &self.config_path {
+            Some(path) => path.clone(),
+            None => crate::find_config_path("flow.toml")?,
+        };
+        log::debug!("reloading config file from `{}`", path.display());
+        let config = Config::from_path(path)?;
+
+        let (tree, root_presses, problems) = self.build_combos(&config);
+        if !problems.is_empty() {
+            let messages: Vec<String> = problems.into_iter().map(|p| p.message).collect();
+            for message in &messages {
+                log::error!("{}", message);
+            }
+            anyhow::bail!(
+                "the config has {} problems, keeping the current config, {}",
+                messages.len(),
+                messages.join(", ")
+            );
+        }
+
+        // the lock is only held to replace the `Arc`, it can't be poisoned
+        *self.config.write().unwrap() = Arc::new(config);
+        self.clone().install_binds(tree, root_presses).await?;
+        self.apply_layouts().await?;
+
+        log::info!("config reloaded");
+        self.emit(IpcEvent::ConfigReloaded);
+        Ok(())
+    }
+
+    /// recreates the layout of every workspace with the current config,
+    /// each workspace keeps its layout kind, then re-tiles the current one
+    async fn apply_layouts(&self) -> anyhow::Result<()> {
+        let config = self.config();
+        let mut workspaces = self.workspaces.lock().await;
+        for workspace in workspaces.iter_mut() {
+            workspace.set_layout(workspace.layout_kind().create(config.layout()));
+        }
+        self.arrange(workspaces.current_mut()).await
+    }
+
+    /// hides the current workspace windows and shows the
+    /// windows of the workspace at the given index
+    async fn view_workspace(&self, index: usize) -> anyhow::Result<()> {
+        let mut workspaces = self.workspaces.lock().await;
+        if workspaces.current_index() == index {
+            return Ok(());
+        }
+
+        log::debug!("switching to workspace {}", index + 1);
+        workspaces.current_mut().hide(&self.atoms).await?;
+        workspaces.set_current(index);
+        self.ewmh.set_current_desktop(index).await?;
+        self.emit(IpcEvent::WorkspaceChanged {
+            workspace: index + 1,
+        });
+
+        let workspace = workspaces.current_mut();
+        self.arrange(workspace).await?;
+        workspace.show(&self.atoms).await?;
+        self.refocus(&mut workspaces).await
+    }
+
+    /// moves the focused window from the current workspace to the
+    /// workspace at the given index, the moved window is hidden
+    async fn move_to_workspace(&self, index: usize) -> anyhow::Result<()> {
+        let focused = self
+            .workspaces
+            .lock()
+            .await
+            .current_mut()
+            .focused()
+            .map(|window| window.id());
+        match focused {
+            Some(focused) => self.move_window_to_workspace(focused, index).await,
+            None => Ok(()),
+        }
+    }
+
+    /// moves a managed window to the workspace at the given index, the window
+    /// is hidden when it leaves the current workspace and shown when it joins it
+    async fn move_window_to_workspace(&self, window: Window, index: usize) -> anyhow::Result<()> {
+        let mut workspaces = self.workspaces.lock().await;
+        let Some(from) = workspaces.workspace_of(window) else {
+            return Ok(());
+        };
+        if from == index {
+            return Ok(());
+        }
+
+        log::debug!("moving window {} to workspace {}", window, index + 1);
+        let current = workspaces.current_index();
+        let Some(moved) = workspaces.move_window(window, index) else {
+            return Ok(());
+        };
+        if from == current {
+            moved.unmap(&self.atoms).await?;
+        }
+        self.ewmh.set_window_desktop(window, index).await?;
+
+        let workspace = workspaces.current_mut();
+        if from == current {
+            self.arrange(workspace).await?;
+            self.refocus(&mut workspaces).await?;
+        } else if index == current {
+            self.arrange(workspace).await?;
+            if let Some(moved) = workspace.window(window) {
+                moved.map(&self.atoms).await?;
+            }
+        }
+        Ok(())
+    }
+
+    #[inline]
+    async fn handle_key_press_event(self: &Arc<Self>, event: KeyPressEvent) {
+        // the global modifier is held for every combo, and the lock
+        // modifiers should not change which combo is executed
+        let modifier = u16::from(ModMask::from(self.config().flow().modifier()));
+        let modifiers = u16::from(event.state) & KEY_MODIFIERS_MASK & !modifier;
+        let press = KeyPress::new(
+            event.detail.into(),
+            (modifiers & !ignored_modifiers(modifier)).into(),
+        );
+
         let combo_snapshot = {
             let mut combo_record = self.combos_record.lock().await;
-            combo_record.add(event.detail.into());
+            combo_record.add(press);
             combo_record.snapshot()
         };
 
-        if let Some(handler) = self
+        // the tree lock is released before the handler is executed
+        // since handlers may need to access the combos
+        let handler = self
             .combos_tree
             .lock()
             .await
-            .find_combo_handler(combo_snapshot)
-        {
+            .find_combo_handler(combo_snapshot);
+
+        if let Some(handler) = handler {
             log::info!("handler found {}", handler.handler_name());
-            let _ = handler.handle().await.inspect_err(|err| {
+            let _ = handler.handle(Arc::clone(self)).await.inspect_err(|err| {
                 log::error!(
                     "handler `{}` returned an error while trying to execute, {}",
                     handler.handler_name(),
@@ -258,33 +911,384 @@ where
             return Ok(());
         }
 
-        self.connection.map_window(event.window).await?;
+        let mut workspaces = self.workspaces.lock().await;
+
+        // a managed window can ask to be mapped again, it is only
+        // shown if its workspace is the current one
+        let current = workspaces.current_index();
+        if let Some(index) = workspaces.workspace_of(event.window) {
+            if index == current
+                && let Some(window) = workspaces.window(event.window)
+            {
+                window.map(&self.atoms).await?;
+            }
+            return Ok(());
+        }
+
+        // short lived windows like splash screens can be destroyed
+        // before their map request is handled
+        let kind = match WindowType::read(&*self.connection, event.window, &self.atoms).await {
+            Err(err) if is_x11_error(&err) => {
+                log::debug!("window {} is gone before it was managed", event.window);
+                return Ok(());
+            }
+            kind => kind?,
+        };
+
+        // docks like panels and bars place themselves, they are shown but not
+        // managed, the windows are tiled around the space they reserve
+        if kind == WindowType::Dock {
+            log::debug!("mapping dock window {}", event.window);
+            self.ewmh.add_dock(event.window, self.screen).await?;
+            self.connection.map_window(event.window).await?;
+            let workspace = workspaces.current_mut();
+            self.arrange(workspace).await?;
+            return self.restack(workspace).await;
+        }
+
+        log::debug!(
+            "managing {:?} window {} in workspace {}",
+            kind,
+            event.window,
+            current + 1
+        );
+        let Some(window) = workspaces.manage(event.window, kind, current) else {
+            return Ok(());
+        };
+        let setup = async {
+            window.setup(&self.atoms).await?;
+            if window.floating() {
+                window
+                    .float(
+                        self.ewmh.work_area(self.screen),
+                        self.config().layout().border_width(),
+                    )
+                    .await?;
+            }
+            anyhow::Ok(())
+        };
+        match setup.await {
+            Err(err) if is_x11_error(&err) => {
+                log::debug!("window {} is gone before it was set up", event.window);
+                workspaces.unmanage(event.window);
+                return Ok(());
+            }
+            setup => setup?,
+        }
+        self.ewmh.add_client(event.window).await?;
+        self.ewmh.set_window_desktop(event.window, current).await?;
+
+        let workspace = workspaces.current_mut();
+        self.arrange(workspace).await?;
+        if let Some(window) = workspace.window(event.window) {
+            window.map(&self.atoms).await?;
+        }
+        self.focus(&mut workspaces, event.window).await
+    }
+
+    /// in focus-follows-mouse mode, the window the pointer enters is focused
+    #[inline]
+    async fn handle_enter_notify_event(&self, event: EnterNotifyEvent) -> anyhow::Result<()> {
+        if self.config().flow().focus() != FocusMode::Mouse || event.mode != NotifyMode::NORMAL {
+            return Ok(());
+        }
+        self.focus_if_visible(event.event).await
+    }
+
+    /// the buttons of managed windows are grabbed, so a click focuses the
+    /// window and then the click is replayed to the window itself
+    #[inline]
+    async fn handle_button_press_event(&self, event: ButtonPressEvent) -> anyhow::Result<()> {
+        self.focus_if_visible(event.event).await?;
+        self.connection
+            .allow_events(Allow::REPLAY_POINTER, event.time)
+            .await?;
+        Ok(())
+    }
+
+    /// focuses the window if it is part of the current workspace
+    /// and it isn't already focused
+    async fn focus_if_visible(&self, window: Window) -> anyhow::Result<()> {
+        let mut workspaces = self.workspaces.lock().await;
+        let workspace = workspaces.current_mut();
+        let focused = workspace.focused().map(|w| w.id());
+        if workspace.contains(window) && focused != Some(window) {
+            self.focus(&mut workspaces, window).await?;
+        }
+        Ok(())
+    }
+
+    /// unmaps caused by flow hiding a window are expected, any other
+    /// unmap means the client withdrew the window
+    #[inline]
+    async fn handle_unmap_notify_event(&self, event: UnmapNotifyEvent) -> anyhow::Result<()> {
+        // a client that withdraws a window that is already unmapped sends
+        // a synthetic `UnmapNotify`, as described in ICCCM
+        let synthetic = event.response_type & 0x80 != 0;
+        if !synthetic {
+            let mut workspaces = self.workspaces.lock().await;
+            let pending = workspaces
+                .window_mut(event.window)
+                .is_some_and(|window| window.take_pending_unmap());
+            if pending {
+                return Ok(());
+            }
+        }
+
+        if let Some(window) = self.forget_window(event.window).await? {
+            window.set_wm_state(&self.atoms, WmState::Withdrawn).await?;
+        }
+        self.forget_dock(event.window).await
+    }
+
+    #[inline]
+    async fn handle_destroy_notify_event(&self, event: DestroyNotifyEvent) -> anyhow::Result<()> {
+        self.forget_window(event.window).await?;
+        self.forget_dock(event.window).await
+    }
+
+    /// stops tracking the dock and gives the space it reserved back to the
+    /// current workspace, the other workspaces are arranged when shown
+    async fn forget_dock(&self, window: Window) -> anyhow::Result<()> {
+        if self.ewmh.remove_dock(window) {
+            log::debug!("dock {} is gone", window);
+            self.arrange(self.workspaces.lock().await.current_mut())
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// managed windows are tiled by the layout, so their requested geometry
+    /// is ignored, windows we don't manage can configure themselves freely
+    #[inline]
+    async fn handle_configure_request_event(
+        &self,
+        event: ConfigureRequestEvent,
+    ) -> anyhow::Result<()> {
+        // floating windows can move and resize themselves, the
+        // other managed windows keep the geometry flow gave them
+        let mut workspaces = self.workspaces.lock().await;
+        if let Some(window) = workspaces.window_mut(event.window) {
+            if window.floating() && !window.fullscreen() {
+                return window.configure_request(&event).await;
+            }
+            return window.send_configure_notify().await;
+        }
+
         self.connection
             .configure_window(
                 event.window,
-                &ConfigureWindowAux {
-                    x: Some(7),
-                    y: Some(7),
-                    width: Some(800),
-                    height: Some(400),
-                    border_width: Some(4),
-                    sibling: None,
-                    stack_mode: None,
-                },
+                &ConfigureWindowAux::from_configure_request(&event),
             )
             .await?;
+        Ok(())
+    }
 
-        // TODO: play and delete
-        self.connection
-            .change_property(
-                PropMode::REPLACE,
-                event.window,
-                AtomEnum::WM_NAME,
-                AtomEnum::STRING,
-                8,
-                5,
-                "hello".as_bytes()
-            ).await?.check().await?;
+    /// handles the EWMH requests of pagers and tools like `wmctrl`, the
+    /// desktops they refer to are the flow workspaces
+    async fn handle_client_message_event(&self, event: ClientMessageEvent) -> anyhow::Result<()> {
+        if event.format != 32 {
+            return Ok(());
+        }
+        let data = event.data.as_data32();
+
+        if event.type_ == self.atoms._NET_CURRENT_DESKTOP {
+            match usize::try_from(data[0]) {
+                Ok(index) if index < WORKSPACES_COUNT => self.view_workspace(index).await?,
+                _ => log::warn!("ignoring request to show desktop {}", data[0]),
+            }
+        } else if event.type_ == self.atoms._NET_WM_DESKTOP {
+            // windows can't be on all the workspaces, so the
+            // `0xFFFFFFFF` desktop is ignored with the invalid ones
+            match usize::try_from(data[0]) {
+                Ok(index) if index < WORKSPACES_COUNT => {
+                    self.move_window_to_workspace(event.window, index).await?
+                }
+                _ => log::warn!(
+                    "ignoring request to move window {} to desktop {}",
+                    event.window,
+                    data[0]
+                ),
+            }
+        } else if event.type_ == self.atoms._NET_ACTIVE_WINDOW {
+            // the source indication is 1 for applications and 2 for pagers,
+            // old clients send 0 and are treated like applications
+            const PAGER_SOURCE: u32 = 2;
+            let policy = match data[0] {
+                PAGER_SOURCE => ActivationPolicy::Focus,
+                _ => self.config().flow().activation(),
+            };
+            self.activate_window(event.window, policy).await?;
+        } else if event.type_ == self.atoms._NET_WM_STATE
+            && (data[1] == self.atoms._NET_WM_STATE_FULLSCREEN
+                || data[2] == self.atoms._NET_WM_STATE_FULLSCREEN)
+        {
+            let Some(fullscreen) = self
+                .workspaces
+                .lock()
+                .await
+                .window(event.window)
+                .map(|window| window.fullscreen())
+            else {
+                return Ok(());
+            };
+
+            // the action is 0 to remove the state, 1 to add it and 2 to toggle it
+            match data[0] {
+                0 => self.set_fullscreen(event.window, false).await?,
+                1 => self.set_fullscreen(event.window, true).await?,
+                2 => self.set_fullscreen(event.window, !fullscreen).await?,
+                action => log::warn!("ignoring unknown `_NET_WM_STATE` action {}", action),
+            }
+        }
         Ok(())
     }
+
+    /// handles an activation request of a managed window with the given policy
+    async fn activate_window(
+        &self,
+        window: Window,
+        policy: ActivationPolicy,
+    ) -> anyhow::Result<()> {
+        let Some(index) = self.workspaces.lock().await.workspace_of(window) else {
+            return Ok(());
+        };
+
+        match policy {
+            ActivationPolicy::Focus => {
+                log::debug!("activating window {}", window);
+                self.view_workspace(index).await?;
+                let mut workspaces = self.workspaces.lock().await;
+                self.focus(&mut workspaces, window).await?;
+            }
+            ActivationPolicy::MarkUrgent => {
+                // the focused window already has the user attention
+                let mut workspaces = self.workspaces.lock().await;
+                let focused = index == workspaces.current_index()
+                    && workspaces.current_mut().focused().map(|w| w.id()) == Some(window);
+                if !focused
+                    && let Some(managed) = workspaces.window_mut(window)
+                    && !managed.urgent()
+                {
+                    log::debug!("marking window {} as urgent", window);
+                    managed.set_urgent(true);
+                    managed
+                        .set_state(
+                            &self.atoms,
+                            self.atoms._NET_WM_STATE_DEMANDS_ATTENTION,
+                            true,
+                        )
+                        .await?;
+                    self.emit(IpcEvent::WindowUrgent {
+                        window,
+                        workspace: index + 1,
+                        urgent: true,
+                    });
+                }
+            }
+            ActivationPolicy::Ignore => {
+                log::debug!("ignoring activation request of window {}", window);
+            }
+        }
+        Ok(())
+    }
+
+    /// tracks the title and hints of managed windows, changes the subscribed
+    /// ipc clients care about are emitted as events, the docks strut changes
+    /// re-tile the current workspace
+    async fn handle_property_notify_event(&self, event: PropertyNotifyEvent) -> anyhow::Result<()> {
+        let mut workspaces = self.workspaces.lock().await;
+        if self.ewmh.is_dock(event.window) {
+            if event.atom == self.atoms._NET_WM_STRUT
+                || event.atom == self.atoms._NET_WM_STRUT_PARTIAL
+            {
+                self.ewmh.add_dock(event.window, self.screen).await?;
+                self.arrange(workspaces.current_mut()).await?;
+            }
+            return Ok(());
+        }
+
+        let Some(index) = workspaces.workspace_of(event.window) else {
+            return Ok(());
+        };
+        let Some(window) = workspaces.window_mut(event.window) else {
+            return Ok(());
+        };
+
+        if event.atom == u32::from(AtomEnum::WM_NAME) || event.atom == self.atoms._NET_WM_NAME {
+            self.emit(IpcEvent::WindowTitleChanged {
+                window: window.id(),
+                title: window.title(&self.atoms).await?,
+            });
+        } else if event.atom == u32::from(AtomEnum::WM_HINTS)
+            || event.atom == self.atoms.WM_PROTOCOLS
+        {
+            let urgent = window.urgent();
+            window.update_hints(&self.atoms).await?;
+            if window.urgent() != urgent {
+                self.emit(IpcEvent::WindowUrgent {
+                    window: window.id(),
+                    workspace: index + 1,
+                    urgent: window.urgent(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// stops managing the window and re-tiles the workspace that
+    /// held it, returns the window if it was managed
+    async fn forget_window(&self, window: Window) -> anyhow::Result<Option<XWindow<C>>> {
+        let mut workspaces = self.workspaces.lock().await;
+        let Some((index, window)) = workspaces.unmanage(window) else {
+            return Ok(None);
+        };
+
+        log::debug!("window {} is no longer managed", window.id());
+        self.ewmh.remove_client(window.id()).await?;
+        if index == workspaces.current_index() {
+            self.arrange(workspaces.current_mut()).await?;
+            self.refocus(&mut workspaces).await?;
+        }
+        Ok(Some(window))
+    }
+}
+
+/// the modifier bits of a key event state, the higher
+/// bits of the state are the mouse buttons
+const KEY_MODIFIERS_MASK: u16 = 0xff;
+const LOCK_MASK: u16 = 1 << 1;
+
+/// num lock is mapped to `Mod2` on almost every keyboard layout
+const NUM_LOCK_MASK: u16 = 1 << 4;
+
+/// the lock modifiers are ignored when matching combos, unless
+/// one of them is used as the global modifier
+#[inline]
+fn ignored_modifiers(global: u16) -> u16 {
+    (LOCK_MASK | NUM_LOCK_MASK) & !global
+}
+
+/// converts the workspace number as the user see it (starting from 1)
+/// to the workspace index
+fn workspace_index(number: usize) -> anyhow::Result<usize> {
+    if !(1..=WORKSPACES_COUNT).contains(&number) {
+        anyhow::bail!(
+            "workspace {} doesn't exist, workspaces are numbered 1 to {}",
+            number,
+            WORKSPACES_COUNT
+        );
+    }
+    Ok(number - 1)
+}
+
+/// true if the error is an error reply of the x11 server, like a `BadWindow`
+/// for a window that was destroyed, rather than a connection error
+fn is_x11_error(err: &anyhow::Error) -> bool {
+    err.chain().any(|err| {
+        matches!(
+            err.downcast_ref::<ReplyError>(),
+            Some(ReplyError::X11Error(_))
+        )
+    })
 }
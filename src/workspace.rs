@@ -1,24 +1,345 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 use x11rb_async::connection::Connection;
+use x11rb_async::protocol::xproto::Window as X11Window;
 
-use crate::window::Window;
+use crate::atoms::Atoms;
+use crate::layout::{Geometry, Layout, LayoutKind};
+use crate::window::{Window, WindowType};
 
 pub struct Workspace<C>
 where
-    C: Connection
+    C: Connection,
 {
     connection: Arc<C>,
-    windows: Vec<Window<C>>
+    windows: Vec<Window<C>>,
+
+    /// the layout that arranges the workspace windows, the
+    /// windows order in `windows` is the order given to the layout
+    layout: Box<dyn Layout>,
+
+    /// index in `windows` of the window that has the focus
+    /// when the workspace is shown
+    focused: Option<usize>,
+
+    /// the windows in the order they were focused, the most recent is last,
+    /// used to refocus the previous window when the focused one is removed
+    focus_history: Vec<X11Window>,
 }
 
 impl<C> Workspace<C>
 where
-    C: Connection + Send
+    C: Connection + Send,
 {
-    pub fn with_connection(connection: Arc<C>) -> Self {
+    pub fn with_connection(connection: Arc<C>, layout: Box<dyn Layout>) -> Self {
         Self {
             connection,
-            windows: Vec::with_capacity(4)
+            windows: Vec::with_capacity(4),
+            layout,
+            focused: None,
+            focus_history: Vec::with_capacity(4),
+        }
+    }
+
+    /// creates a new `Window` for the given x11 window and
+    /// appends it to the end of the workspace windows
+    pub fn add_window(&mut self, window: X11Window, kind: WindowType) -> &mut Window<C> {
+        self.insert_window(Window::new(self.connection.clone(), window, kind))
+    }
+
+    /// appends an existing window, used when a window is moved between
+    /// workspaces, the window is focused if no other window is
+    /// and the window can be focused
+    pub fn insert_window(&mut self, window: Window<C>) -> &mut Window<C> {
+        let focusable = window.focusable();
+        let index = match self.position(window.id()) {
+            Some(index) => index,
+            None => {
+                self.windows.push(window);
+                self.windows.len() - 1
+            }
+        };
+        if self.focused.is_none() && focusable {
+            self.focused = Some(index);
+        }
+        &mut self.windows[index]
+    }
+
+    /// removes the window from the workspace, returns `None` if the window isn't
+    /// part of the workspace, if the removed window was focused the most
+    /// recently focused window gets the focus
+    pub fn remove_window(&mut self, window: X11Window) -> Option<Window<C>> {
+        let index = self.windows.iter().position(|w| w.id() == window)?;
+        let removed = self.windows.remove(index);
+        self.focus_history.retain(|&w| w != window);
+
+        self.focused = match self.focused {
+            Some(focused) if focused == index => self
+                .focus_history
+                .last()
+                .and_then(|&recent| self.position(recent))
+                .or_else(|| self.windows.iter().position(|w| w.focusable())),
+            Some(focused) if focused > index => Some(focused - 1),
+            focused => focused,
+        };
+        Some(removed)
+    }
+
+    #[inline]
+    fn position(&self, window: X11Window) -> Option<usize> {
+        self.windows.iter().position(|w| w.id() == window)
+    }
+
+    /// returns the window that has the focus in the workspace
+    #[inline]
+    pub fn focused(&self) -> Option<&Window<C>> {
+        self.focused.and_then(|index| self.windows.get(index))
+    }
+
+    /// marks the given window as the focused window of the workspace, returns
+    /// false if the window isn't part of the workspace or can't be focused
+    pub fn set_focused(&mut self, window: X11Window) -> bool {
+        let Some(index) = self
+            .position(window)
+            .filter(|&index| self.windows[index].focusable())
+        else {
+            return false;
+        };
+        self.focused = Some(index);
+        self.focus_history.retain(|&w| w != window);
+        self.focus_history.push(window);
+        true
+    }
+
+    #[inline]
+    pub fn contains(&self, window: X11Window) -> bool {
+        self.windows.iter().any(|w| w.id() == window)
+    }
+
+    #[inline]
+    pub fn window(&self, window: X11Window) -> Option<&Window<C>> {
+        self.windows.iter().find(|w| w.id() == window)
+    }
+
+    /// the workspace windows in the order given to the layout
+    #[inline]
+    pub fn windows(&self) -> &[Window<C>] {
+        &self.windows
+    }
+
+    #[inline]
+    pub fn window_mut(&mut self, window: X11Window) -> Option<&mut Window<C>> {
+        self.windows.iter_mut().find(|w| w.id() == window)
+    }
+
+    #[inline]
+    pub fn layout_kind(&self) -> LayoutKind {
+        self.layout.kind()
+    }
+
+    #[inline]
+    pub fn set_layout(&mut self, layout: Box<dyn Layout>) {
+        self.layout = layout;
+    }
+
+    /// returns the window that is `offset` places away from the focused
+    /// window, wrapping around the workspace windows that can be focused,
+    /// if no window is focused the first of those windows is returned
+    pub fn cycle(&self, offset: isize) -> Option<&Window<C>> {
+        let focusable: Vec<&Window<C>> = self.windows.iter().filter(|w| w.focusable()).collect();
+        let Some(index) = self
+            .focused()
+            .and_then(|focused| focusable.iter().position(|w| w.id() == focused.id()))
+        else {
+            return focusable.first().copied();
+        };
+        let index = (index as isize + offset).rem_euclid(focusable.len() as isize);
+        focusable.get(index as usize).copied()
+    }
+
+    /// swaps the focused window with the first tiled window, which is the
+    /// first master window in layouts that have a master, the focus stays
+    /// with the swapped window, floating and fullscreen windows are not
+    /// tiled so they are never swapped
+    pub fn swap_focused_with_master(&mut self) {
+        let tiled = |w: &Window<C>| !w.floating() && !w.fullscreen();
+        let master = self.windows.iter().position(tiled);
+        let focused = self.focused.filter(|&index| tiled(&self.windows[index]));
+        if let (Some(index), Some(master)) = (focused, master) {
+            self.windows.swap(master, index);
+            self.focused = Some(master);
+        }
+    }
+
+    /// maps all the workspace windows
+    pub async fn show(&self, atoms: &Atoms) -> anyhow::Result<()> {
+        for window in &self.windows {
+            window.map(atoms).await?;
+        }
+        Ok(())
+    }
+
+    /// unmaps all the workspace windows, the windows are still
+    /// managed by the workspace and will be mapped again by `show`
+    pub async fn hide(&mut self, atoms: &Atoms) -> anyhow::Result<()> {
+        for window in &mut self.windows {
+            window.unmap(atoms).await?;
+        }
+        Ok(())
+    }
+
+    /// asks the layout for the windows geometries inside the given area and
+    /// configures each window accordingly, floating and fullscreen windows are
+    /// left out of the layout and fullscreen windows cover the whole screen
+    pub async fn arrange(
+        &mut self,
+        screen: Geometry,
+        area: Geometry,
+        border_width: u32,
+    ) -> anyhow::Result<()> {
+        let ids: Vec<X11Window> = self
+            .windows
+            .iter()
+            .filter(|w| !w.floating() && !w.fullscreen())
+            .map(|w| w.id())
+            .collect();
+        let geometries = self.layout.arrange(area, &ids);
+        log::debug!(
+            "arranging {} windows with `{}` layout",
+            ids.len(),
+            self.layout.layout_name()
+        );
+
+        for (id, geometry) in geometries {
+            if let Some(window) = self.windows.iter_mut().find(|w| w.id() == id) {
+                window.configure(geometry, border_width).await?;
+            }
+        }
+        for window in self.windows.iter_mut().filter(|w| w.fullscreen()) {
+            window.configure(screen, 0).await?;
+        }
+        Ok(())
+    }
+}
+
+/// the number of workspaces flow manages
+pub const WORKSPACES_COUNT: usize = 9;
+
+/// holds all the workspaces and remembers which one is
+/// currently shown on the screen and which one was shown before it
+pub struct Workspaces<C>
+where
+    C: Connection,
+{
+    workspaces: [Workspace<C>; WORKSPACES_COUNT],
+    current: usize,
+    previous: usize,
+
+    /// registry of all the managed windows, maps each window to
+    /// the index of the workspace that holds it
+    clients: HashMap<X11Window, usize>,
+}
+
+impl<C> Workspaces<C>
+where
+    C: Connection + Send,
+{
+    pub fn new(workspaces: [Workspace<C>; WORKSPACES_COUNT]) -> Self {
+        Self {
+            workspaces,
+            current: 0,
+            previous: 0,
+            clients: HashMap::new(),
+        }
+    }
+
+    /// starts managing the window as part of the workspace at the given
+    /// index, returns `None` if the window is already managed
+    pub fn manage(
+        &mut self,
+        window: X11Window,
+        kind: WindowType,
+        index: usize,
+    ) -> Option<&mut Window<C>> {
+        if self.clients.contains_key(&window) || index >= WORKSPACES_COUNT {
+            return None;
+        }
+        self.clients.insert(window, index);
+        Some(self.workspaces[index].add_window(window, kind))
+    }
+
+    /// stops managing the window, returns the index of the workspace
+    /// that held it with the window itself
+    pub fn unmanage(&mut self, window: X11Window) -> Option<(usize, Window<C>)> {
+        let index = self.clients.remove(&window)?;
+        let window = self.workspaces[index].remove_window(window)?;
+        Some((index, window))
+    }
+
+    /// moves a managed window to the workspace at the given index
+    pub fn move_window(&mut self, window: X11Window, index: usize) -> Option<&mut Window<C>> {
+        let current = *self.clients.get(&window)?;
+        if index >= WORKSPACES_COUNT {
+            return None;
+        }
+        if current != index {
+            let moved = self.workspaces[current].remove_window(window)?;
+            self.workspaces[index].insert_window(moved);
+            self.clients.insert(window, index);
+        }
+        self.workspaces[index].window_mut(window)
+    }
+
+    /// returns the index of the workspace that holds the window
+    #[inline]
+    pub fn workspace_of(&self, window: X11Window) -> Option<usize> {
+        self.clients.get(&window).copied()
+    }
+
+    #[inline]
+    pub fn window(&self, window: X11Window) -> Option<&Window<C>> {
+        let index = self.workspace_of(window)?;
+        self.workspaces[index].window(window)
+    }
+
+    #[inline]
+    pub fn window_mut(&mut self, window: X11Window) -> Option<&mut Window<C>> {
+        let index = self.workspace_of(window)?;
+        self.workspaces[index].window_mut(window)
+    }
+
+    /// the index of the currently shown workspace
+    #[inline]
+    pub fn current_index(&self) -> usize {
+        self.current
+    }
+
+    #[inline]
+    pub fn previous_index(&self) -> usize {
+        self.previous
+    }
+
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = &Workspace<C>> {
+        self.workspaces.iter()
+    }
+
+    #[inline]
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Workspace<C>> {
+        self.workspaces.iter_mut()
+    }
+
+    #[inline]
+    pub fn current_mut(&mut self) -> &mut Workspace<C> {
+        &mut self.workspaces[self.current]
+    }
+
+    /// marks the given workspace as the current one, the caller is
+    /// responsible for hiding the old workspace and showing the new one
+    pub fn set_current(&mut self, index: usize) {
+        if index != self.current {
+            self.previous = self.current;
+            self.current = index;
         }
     }
 }
@@ -0,0 +1,46 @@
+/// the XDG base directories, the environment variables must hold absolute
+/// paths, relative ones are ignored as the specification requires
+use std::env;
+use std::path::PathBuf;
+
+fn absolute_var(name: &str) -> Option<PathBuf> {
+    env::var_os(name)
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+}
+
+fn home() -> Option<PathBuf> {
+    absolute_var("HOME")
+}
+
+/// `$XDG_CONFIG_HOME`, defaults to `$HOME/.config`
+pub fn config_home() -> Option<PathBuf> {
+    absolute_var("XDG_CONFIG_HOME").or_else(|| Some(home()?.join(".config")))
+}
+
+/// `$XDG_CONFIG_DIRS` in order of preference, defaults to `/etc/xdg`
+pub fn config_dirs() -> Vec<PathBuf> {
+    let dirs: Vec<PathBuf> = env::var_os("XDG_CONFIG_DIRS")
+        .map(|dirs| {
+            env::split_paths(&dirs)
+                .filter(|path| path.is_absolute())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    if dirs.is_empty() {
+        vec![PathBuf::from("/etc/xdg")]
+    } else {
+        dirs
+    }
+}
+
+/// `$XDG_STATE_HOME`, defaults to `$HOME/.local/state`
+pub fn state_home() -> Option<PathBuf> {
+    absolute_var("XDG_STATE_HOME").or_else(|| Some(home()?.join(".local").join("state")))
+}
+
+/// `$XDG_RUNTIME_DIR`, it has no default
+pub fn runtime_dir() -> Option<PathBuf> {
+    absolute_var("XDG_RUNTIME_DIR")
+}